@@ -1,9 +1,9 @@
 .start [0]
     jmp main
 msg1:
-.asciiz "Enter a number. This will return double of it: "
+.ascii "Enter a number. This will return double of it: "
 msg2:
-.asciiz "Double of your number is: "
+.ascii "Double of your number is: "
 main:
     lea r0, msg1
     lea r1, msg2
@@ -3,14 +3,14 @@ const newline = 10
     int 71   ; don't push returns
     jmp start
 msg2:
-    .asciiz "The number entered is too large.\n"
+    .ascii "The number entered is too large.\n"
 text:
-    .asciiz "The number entered is too small.\n"
+    .ascii "The number entered is too small.\n"
 msg:
-    .asciiz "The golden ratio is: "
+    .ascii "The golden ratio is: "
 
 msg_end:
-    .asciiz "Enter how many numbers to calculate (max 23): "
+    .ascii "Enter how many numbers to calculate (max 23): "
 
 start:
     mov r6, 0 ; move 0 into register 6
@@ -21,21 +21,21 @@ start:
     int 10
     .pad 235
 hello:
-    .asciiz "         -/oyddmdhs+:.                                                     \n"
-    .asciiz "     -odNMMMMMMMMNNmhy+-`                                                  \n"
-    .asciiz "   -yNMMMMMMMMMMMNNNmmdhy+-                                                \n"
-    .asciiz " omMMMMMMMMMMMNhhyyyohmdddhhhdo`                                           \n"
-    .asciiz ".ydMMMMMMMMMMdhs++so/smdddhhhhdm+`                                         \n"
-    .asciiz " oyhdmNMMMMMMMNdyooydmddddhhhhyhNd.                                        \n"
-    .asciiz "  :oyhhdNNMMMMMMMNNNmmdddhhhhhyymMh          Powered by                    \n"
-    .asciiz "    .:+sydNMMMMMNNNmmmdddhhhhhhmMmy                                        \n"
-    .asciiz "       /mMMMMMMNNNmmmdddhhhhhmMNhs:         Gentoo Linux!!                 \n"
-    .asciiz "    `oNMMMMMMMNNNmmmddddhhdmMNhs+`                                         \n"
-    .asciiz "  `sNMMMMMMMMNNNmmmdddddmNMmhs/.                                           \n"
-    .asciiz " /NMMMMMMMMNNNNmmmdddmNMNdso:`                                             \n"
-    .asciiz "+MMMMMMMNNNNNmmmmdmNMNdso/-                                                \n"
-    .asciiz "yMMNNNNNNNmmmmmNNMmhs+/-`                                                  \n"
-    .asciiz "/hMMNNNNNNNNMNdhs++/-`                                                     \n"
-    .asciiz "`/ohdmmddhys+++/:.`                                                        \n"
-    .asciiz " `-//////:--.                                                              \n"
+    .ascii "         -/oyddmdhs+:.                                                     \n"
+    .ascii "     -odNMMMMMMMMNNmhy+-`                                                  \n"
+    .ascii "   -yNMMMMMMMMMMMNNNmmdhy+-                                                \n"
+    .ascii " omMMMMMMMMMMMNhhyyyohmdddhhhdo`                                           \n"
+    .ascii ".ydMMMMMMMMMMdhs++so/smdddhhhhdm+`                                         \n"
+    .ascii " oyhdmNMMMMMMMNdyooydmddddhhhhyhNd.                                        \n"
+    .ascii "  :oyhhdNNMMMMMMMNNNmmdddhhhhhyymMh          Powered by                    \n"
+    .ascii "    .:+sydNMMMMMNNNmmmdddhhhhhhmMmy                                        \n"
+    .ascii "       /mMMMMMMNNNmmmdddhhhhhmMNhs:         Gentoo Linux!!                 \n"
+    .ascii "    `oNMMMMMMMNNNmmmddddhhdmMNhs+`                                         \n"
+    .ascii "  `sNMMMMMMMMNNNmmmdddddmNMmhs/.                                           \n"
+    .ascii " /NMMMMMMMMNNNNmmmdddmNMNdso:`                                             \n"
+    .ascii "+MMMMMMMNNNNNmmmmdmNMNdso/-                                                \n"
+    .ascii "yMMNNNNNNNmmmmmNNMmhs+/-`                                                  \n"
+    .ascii "/hMMNNNNNNNNMNdhs++/-`                                                     \n"
+    .ascii "`/ohdmmddhys+++/:.`                                                        \n"
+    .ascii " `-//////:--.                                                              \n"
 hello_end:
@@ -0,0 +1,263 @@
+use crate::*;
+use colored::*;
+use std::ops::Range;
+
+/// A single assembly session
+///
+/// Every symbol table, the start address and the metadata string live here
+/// instead of in process-wide statics, so that independent programs can be
+/// assembled one after another (or concurrently) without leaking symbols
+/// between runs.
+#[derive(Debug, Clone)]
+pub struct Assembler {
+    pub options: AssembleOptions,
+    pub v_map: ConstMap,
+    pub label_map: LabelMap,
+    pub macro_map: MacroMap,
+    // the @defines read so far, kept across included files
+    pub defines: DefineMap,
+    // the @includes of the files being read, outermost first
+    pub including: Vec<(String, Range<usize>)>,
+    // each file read through an @include, and the @include that first read it
+    pub includes: SymbolTable<(String, Range<usize>)>,
+    pub uses: SymbolUses,
+    pub start_location: i64,
+    pub metadata_str: String,
+    // labels declared .global, and where
+    pub globals: SymbolTable<(String, Range<usize>)>,
+    // address fields left for the linker, when assembling an object
+    pub relocations: Vec<Relocation>,
+    pub diagnostics: Vec<Diagnostic>,
+    // where each word of the last assemble_source came from
+    pub origins: Vec<WordOrigin>,
+    // the words of it that only fill a gap, from .org, .align, .pad and .space
+    pub gaps: Vec<Range<usize>>,
+}
+
+/// The source that produced an encoded word
+#[derive(Debug, Clone, PartialEq)]
+pub struct WordOrigin {
+    pub file: String,
+    pub span: Range<usize>,
+    // the macro calls the word was expanded from, outermost first
+    pub expansion: Vec<ExpansionSite>,
+    // which of the encoded tokens it came from, as copies of a line share a span
+    pub token: usize,
+}
+
+/// One comma-separated value of a `.word`
+///
+/// Literals and constants are summed into `addend` when the directive is
+/// read, and the label's address, if there is one, is added in codegen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WordValue {
+    pub label: Option<(String, Range<usize>)>,
+    pub addend: i64,
+    pub place: Range<usize>,
+}
+
+/// The result of a successful assembly
+#[derive(Debug, Clone)]
+pub struct AssembledProgram {
+    pub source: String,
+    pub binary: Vec<i16>,
+    pub origins: Vec<WordOrigin>,
+    // indices of the words that only fill a gap, which need not be written
+    pub gaps: Vec<Range<usize>>,
+    pub start_location: i64,
+    pub entry_point: i64,
+    pub metadata_str: String,
+    pub v_map: ConstMap,
+    pub label_map: LabelMap,
+    pub macro_map: MacroMap,
+    pub globals: SymbolTable<(String, Range<usize>)>,
+    // each included file, and the @include that first read it
+    pub includes: SymbolTable<(String, Range<usize>)>,
+    pub relocations: Vec<Relocation>,
+    pub warnings: Vec<Diagnostic>,
+}
+
+impl Default for Assembler {
+    fn default() -> Self {
+        Self::with_options(AssembleOptions::default())
+    }
+}
+
+impl Assembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_options(options: AssembleOptions) -> Self {
+        let mut v_map = ConstMap::new();
+        let mut diagnostics = Vec::new();
+        for (name, value) in &options.defines {
+            if let Some((_, _, first)) = v_map.get(name) {
+                diagnostics.push(
+                    Diagnostic::error(
+                        DiagnosticCode::DuplicateConstant,
+                        format!(
+                            "constant `{}` is defined twice on the command line, as {first} and then {value}",
+                            name.magenta()
+                        ),
+                    )
+                    .with_help("define each constant once"),
+                );
+                continue;
+            }
+            v_map.insert(name.clone(), (COMMAND_LINE.to_string(), 0..0, *value));
+        }
+        Assembler {
+            options,
+            v_map,
+            label_map: LabelMap::new(),
+            macro_map: MacroMap::new(),
+            defines: DefineMap::new(),
+            including: Vec::new(),
+            includes: SymbolTable::new(),
+            uses: SymbolUses::default(),
+            start_location: 100,
+            metadata_str: String::new(),
+            globals: SymbolTable::new(),
+            relocations: Vec::new(),
+            diagnostics,
+            origins: Vec::new(),
+            gaps: Vec::new(),
+        }
+    }
+
+    /// Runs every stage of the pipeline over `input`, the contents of `file`
+    ///
+    /// Returns the encoded words - anything that went wrong along the way is
+    /// collected in `diagnostics` rather than printed.
+    pub fn assemble_source(&mut self, file: &str, input: &str) -> Vec<i16> {
+        if self.options.verbose {
+            print_msg!("RAW INPUT");
+            println!("{input}");
+        }
+
+        let mut parser = create_parser(self, file, input);
+        let mut toks = parse_tokens(self, &mut parser);
+
+        self.process_includes(&mut toks);
+        self.process_repeats(&mut toks);
+        self.process_macros(&mut toks);
+        self.lint_tokens(&toks);
+        self.process_start(&mut toks);
+
+        if self.options.verbose {
+            print_msg!("COMPLETE TOKENS");
+            for (_, f, _) in &toks {
+                println!("{f}");
+            }
+        }
+
+        let toks: Vec<(String, TokenKind, Range<usize>)> = toks
+            .into_iter()
+            .filter(|(_, x, _)| !matches!(x, TokenKind::Newline))
+            .collect();
+
+        let mut binary = Vec::new();
+        let mut origins = Vec::new();
+        let mut gaps = Vec::new();
+        for (ind, (fname, tok, span)) in toks.iter().enumerate() {
+            match self.encode((fname, tok, span), fname, &toks.get(ind + 1)) {
+                Ok(value) => {
+                    if self.options.output_format == OutputFormat::Object {
+                        self.record_relocations(binary.len(), tok);
+                    }
+                    let origin = WordOrigin {
+                        file: fname.to_string(),
+                        span: span.clone(),
+                        expansion: match tok {
+                            TokenKind::Instruction(ins) => ins.expansion.clone(),
+                            _ => Vec::new(),
+                        },
+                        token: ind,
+                    };
+                    origins.extend(std::iter::repeat_n(origin, value.len()));
+                    if let TokenKind::Placed(placed) = tok {
+                        if matches!(placed.name.as_str(), "org" | "align" | "pad" | "space") {
+                            gaps.push(binary.len()..binary.len() + value.len());
+                        }
+                    }
+                    binary.extend(value);
+                }
+                Err(e) => match tok {
+                    TokenKind::Instruction(ins) => {
+                        self.diagnostics.push(with_backtrace(e, &ins.expansion))
+                    }
+                    _ => self.diagnostics.push(e),
+                },
+            }
+        }
+        self.origins = origins;
+        self.gaps = gaps;
+        binary
+    }
+
+    // notes every label the words from `offset` refer to, for the linker to fill in
+    fn record_relocations(&mut self, offset: usize, tok: &TokenKind) {
+        match tok {
+            TokenKind::Instruction(ins) => {
+                let Some(kind) = RelocationKind::for_instruction(&ins.name) else {
+                    return;
+                };
+                for (arg, _) in &ins.operands {
+                    if let InstructionArgument::Ident(symbol) = arg {
+                        self.relocations.push(Relocation {
+                            offset,
+                            kind,
+                            symbol: symbol.to_string(),
+                        });
+                    }
+                }
+            }
+            TokenKind::Placed(PlacedDirective {
+                words: DirectiveWords::Values(values),
+                ..
+            }) => {
+                for (i, value) in values.iter().enumerate() {
+                    if let Some((symbol, _)) = &value.label {
+                        self.relocations.push(Relocation {
+                            offset: offset + i,
+                            kind: RelocationKind::Word16,
+                            symbol: symbol.to_string(),
+                        });
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Drops every error past `options.error_limit`, with a note saying so
+    pub fn apply_error_limit(&mut self) {
+        if let Some(limit) = self.options.error_limit {
+            limit_errors(&mut self.diagnostics, limit);
+        }
+    }
+}
+
+/// Drops every error past the first `limit`, with a note saying how many
+/// more there were
+pub fn limit_errors(diagnostics: &mut Vec<Diagnostic>, limit: usize) {
+    let mut errors = 0;
+    diagnostics.retain(|d| {
+        errors += d.is_error() as usize;
+        !d.is_error() || errors <= limit
+    });
+    if errors > limit {
+        diagnostics.push(
+            Diagnostic::new(
+                Severity::Note,
+                DiagnosticCode::ErrorLimit,
+                format!(
+                    "stopped after {limit} errors, {} more not shown",
+                    errors - limit
+                ),
+            )
+            .with_help("raise the limit with `--error-limit`, or set it to 0 to show everything"),
+        );
+    }
+}
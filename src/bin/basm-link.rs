@@ -0,0 +1,77 @@
+use basm::*;
+use clap::Parser;
+use colored::*;
+use std::fs;
+
+fn main() {
+    let args = LinkArgs::parse();
+    let options = AssembleOptions::from(&args);
+
+    if args.format == OutputFormat::Object {
+        eprintln!(
+            "{}: objects can't be linked into another object",
+            "error".bright_red()
+        );
+        std::process::exit(1);
+    }
+    let Ok(base) = u16::try_from(args.base) else {
+        eprintln!(
+            "{}: base address {} is out of range",
+            "error".bright_red(),
+            args.base
+        );
+        std::process::exit(1);
+    };
+
+    let mut objects = Vec::new();
+    let mut errors = Vec::new();
+    for path in &args.objects {
+        let object = fs::read(path)
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| ObjectFile::read(&bytes).map_err(|e| e.to_string()));
+        match object {
+            Ok(object) => objects.push((path.to_string(), object)),
+            Err(e) => errors.push(Diagnostic::error(
+                DiagnosticCode::InvalidObject,
+                format!("cannot read object \"{}\": {e}", path.bold().magenta()),
+            )),
+        }
+    }
+    if errors.is_empty() {
+        let linked = link(&objects, base, args.entry.as_deref()).and_then(|program| {
+            if matches!(args.format, OutputFormat::CArray | OutputFormat::RustArray) {
+                let clashes = array_name_clashes(&program.label_map, &options.array_name);
+                if !clashes.is_empty() {
+                    return Err(clashes);
+                }
+            }
+            Ok(program)
+        });
+        match linked {
+            Ok(program) => {
+                write_output(&args.output, &program.render(&options));
+                if let Some(symbols) = &args.symbols {
+                    write_output(
+                        symbols,
+                        program.render_symbols(args.symbol_format).as_bytes(),
+                    );
+                }
+                return;
+            }
+            Err(e) => errors = e,
+        }
+    }
+    for error in &errors {
+        println!("{error}\n");
+    }
+    print_errors(errors.len(), errors.len());
+    std::process::exit(1);
+}
+
+fn write_output(path: &str, bytes: &[u8]) {
+    if let Err(e) = fs::write(path, bytes) {
+        eprintln!("{}: {e}", "error writing to output".bright_red());
+        print_errors(1, 1);
+        std::process::exit(1);
+    }
+}
@@ -28,119 +28,159 @@ pub const POP_TYPE: u8 = 3;
 pub const LD_TYPE: u8 = 4;
 pub const ST_TYPE: u8 = 5;
 pub const MOV_TYPE_ONE: u8 = 6;
-type CodeGenError = ParserError;
 use std::ops::Range;
-type CodeGenResult = Result<Vec<i16>, (Box<CodeGenError>, Vec<(String, Range<usize>)>)>;
-pub fn encode(
-    ins: (&String, &TokenKind, &Range<usize>),
-    fname: &String,
-    next_ins: &Option<&(String, TokenKind, Range<usize>)>,
-) -> CodeGenResult {
-    let mut encoded_tokens = Vec::new();
-    match &ins.1 {
-        TokenKind::Instruction(ins) => {
-            let (opcode, ins_class) = match ins.name.to_lowercase().as_str() {
-                // all instructions should be valid when this is reached, as it is validated in
-                // validator/validator_ins.rs. therefore, it is fine to use MOV_TYPE for PUSH and INT
-                // because the argument types and counts are validated.
-                //
-                //
-                // But, I do need to check labels as they are not detected in previous instances
-                //
-                // I should also allow it to reference CONSTs too, and see if it is talking about a label
-                // or a CONST (check labels first)
-                //
-                // ^^^^ I need to validate that there are no duplicate names across symbol tabels for
-                // labels and constants
-                "hlt" => (HLT_OP, HLT_TYPE),
-                "add" => (ADD_OP, MOV_TYPE),
-                "bo" => (BO_OP, B_TYPE),
-                "bno" => (BNO_OP, B_TYPE),
-                "pop" => (POP_OP, POP_TYPE),
-                "div" => (DIV_OP, MOV_TYPE),
-                "ret" => (RET_OP, HLT_TYPE),
-                "bl" => (BL_OP, B_TYPE),
-                "bg" => (BG_OP, B_TYPE),
-                "ld" => (LD_OP, LD_TYPE),
-                "st" => (ST_OP, ST_TYPE),
-                "jmp" => (JMP_OP, B_TYPE),
-                "bz" => (BZ_OP, B_TYPE),
-                "bnz" => (BNZ_OP, B_TYPE),
-                "cmp" => (CMP_OP, MOV_TYPE),
-                "nand" => (NAND_OP, MOV_TYPE),
-                "push" => (PUSH_OP, MOV_TYPE_ONE),
-                "int" => (INT_OP, MOV_TYPE_ONE),
-                "mov" => (MOV_OP, MOV_TYPE),
-                "lea" => (LEA_OP, LD_TYPE),
-                _ => gen_ice!(
-                    "INSTRUCTION MATCH FAILED: {} WAS NOT RECOGNIZED.",
-                    ins.name.to_uppercase().magenta()
-                ),
-            };
-            encoded_tokens.push(encode_instruction(
-                fname,
-                &opcode,
-                &ins_class,
-                &ins.operands,
-            )?);
-        }
-        TokenKind::Directive(name) => match name.to_lowercase().as_str() {
-            "asciiz" => {
-                let stri = match next_ins {
-                    Some(thing) => thing.1.to_string(),
-                    None => "no".to_string(),
+type CodeGenResult = Result<Vec<i16>, Diagnostic>;
+impl Assembler {
+    pub fn encode(
+        &self,
+        ins: (&String, &TokenKind, &Range<usize>),
+        fname: &str,
+        next_ins: &Option<&(String, TokenKind, Range<usize>)>,
+    ) -> CodeGenResult {
+        let mut encoded_tokens = Vec::new();
+        match &ins.1 {
+            TokenKind::Instruction(ins) => {
+                let (opcode, ins_class) = match ins.name.to_lowercase().as_str() {
+                    // all instructions should be valid when this is reached, as it is validated in
+                    // validator/validator_ins.rs. therefore, it is fine to use MOV_TYPE for PUSH and INT
+                    // because the argument types and counts are validated.
+                    //
+                    //
+                    // But, I do need to check labels as they are not detected in previous instances
+                    //
+                    // I should also allow it to reference CONSTs too, and see if it is talking about a label
+                    // or a CONST (check labels first)
+                    //
+                    // ^^^^ I need to validate that there are no duplicate names across symbol tabels for
+                    // labels and constants
+                    "hlt" => (HLT_OP, HLT_TYPE),
+                    "add" => (ADD_OP, MOV_TYPE),
+                    "bo" => (BO_OP, B_TYPE),
+                    "bno" => (BNO_OP, B_TYPE),
+                    "pop" => (POP_OP, POP_TYPE),
+                    "div" => (DIV_OP, MOV_TYPE),
+                    "ret" => (RET_OP, HLT_TYPE),
+                    "bl" => (BL_OP, B_TYPE),
+                    "bg" => (BG_OP, B_TYPE),
+                    "ld" => (LD_OP, LD_TYPE),
+                    "st" => (ST_OP, ST_TYPE),
+                    "jmp" => (JMP_OP, B_TYPE),
+                    "bz" => (BZ_OP, B_TYPE),
+                    "bnz" => (BNZ_OP, B_TYPE),
+                    "cmp" => (CMP_OP, MOV_TYPE),
+                    "nand" => (NAND_OP, MOV_TYPE),
+                    "push" => (PUSH_OP, MOV_TYPE_ONE),
+                    "int" => (INT_OP, MOV_TYPE_ONE),
+                    "mov" => (MOV_OP, MOV_TYPE),
+                    "lea" => (LEA_OP, LD_TYPE),
+                    _ => gen_ice!(
+                        "INSTRUCTION MATCH FAILED: {} WAS NOT RECOGNIZED.",
+                        ins.name.to_uppercase().magenta()
+                    ),
                 };
-                if let Some((_, TokenKind::StringLit(_), _)) = next_ins {
-                    for letter in next_ins.unwrap().1.get_str().chars() {
-                        encoded_tokens.push(letter as i16);
-                    }
-                } else {
-                    return Err((
-                        Box::new(CodeGenError {
-                            file: fname.to_string(),
-                            help: Some(format!("found {} argument", stri.magenta())),
-                            input: read_file(fname),
-                            message: String::from(
-                                "ASCIIZ directive must be succeeded by string literal",
-                            ),
-                            start_pos: ins.2.start,
-                            last_pos: ins.2.end,
-                        }),
-                        vec![],
-                    ));
-                }
+                encoded_tokens.push(self.encode_instruction(
+                    fname,
+                    &opcode,
+                    &ins_class,
+                    &ins.operands,
+                )?);
             }
-            "pad" => {
-                let stri = match next_ins {
-                    Some(thing) => thing.1.to_string(),
-                    None => "no".to_string(),
-                };
-                if let Some((_, TokenKind::IntLit(num), _)) = next_ins {
-                    encoded_tokens.extend(vec![0; *num as usize]);
-                } else {
-                    return Err((
-                        Box::new(CodeGenError {
-                            file: fname.to_string(),
-                            help: Some(format!("found {} argument", stri.magenta())),
-                            input: read_file(fname),
-                            message: String::from(
-                                "PAD directive must be succeeded by integer literal",
+            TokenKind::Directive(name) => match name.to_lowercase().as_str() {
+                "ascii" | "asciiz" | "packed" => {
+                    let stri = match next_ins {
+                        Some(thing) => thing.1.to_string(),
+                        None => "no".to_string(),
+                    };
+                    if let Some((_, TokenKind::StringLit(text), _)) = next_ins {
+                        match string_words(&name.to_lowercase(), text) {
+                            Ok(words) => encoded_tokens.extend(words),
+                            Err(c) => gen_ice!("CHARACTER {c:?} WAS NOT CHECKED BEFORE CODEGEN"),
+                        }
+                    } else {
+                        return Err(Diagnostic::error(
+                            DiagnosticCode::InvalidDirective,
+                            format!(
+                                "{} directive must be succeeded by string literal",
+                                name.to_uppercase()
                             ),
-                            start_pos: ins.2.start,
-                            last_pos: ins.2.end,
-                        }),
-                        vec![],
-                    ));
+                        )
+                        .at(fname, ins.2.clone())
+                        .with_help(format!("found {} argument", stri.magenta())));
+                    }
                 }
-            }
-            "word" => {
-                encoded_tokens.push(next_ins.unwrap().1.get_value() as i16);
-            }
-            "start" | "data" => (),
-            _ => gen_ice!("DIRECTIVE MATCH FAILED: {name} NOT RECOGNIZED"),
-        },
-        _ => {}
+                "start" | "data" | "global" => (),
+                _ => gen_ice!("DIRECTIVE MATCH FAILED: {name} NOT RECOGNIZED"),
+            },
+            TokenKind::Placed(placed) => match &placed.words {
+                DirectiveWords::Fill { count, value } => {
+                    encoded_tokens.extend(vec![*value; *count]);
+                }
+                DirectiveWords::Binary(words) => encoded_tokens.extend(words),
+                DirectiveWords::Values(values) => {
+                    for value in values {
+                        let address = match &value.label {
+                            // filled in by the linker, which adds it to the word
+                            Some(_) if self.options.output_format == OutputFormat::Object => 0,
+                            Some((name, place)) => match self.label_map.get(name) {
+                                Some((_, _, address)) => *address as i64,
+                                None => {
+                                    let (info, similars) = self.find_similar_entries(name);
+                                    return Err(Diagnostic::error(
+                                        DiagnosticCode::UnknownLabel,
+                                        format!("cannot find label \"{name}\""),
+                                    )
+                                    .at(fname, place.clone())
+                                    .with_help_opt(info)
+                                    .with_labels(similars));
+                                }
+                            },
+                            None => 0,
+                        };
+                        let word = address + value.addend;
+                        if !(i16::MIN as i64..=u16::MAX as i64).contains(&word) {
+                            return Err(Diagnostic::error(
+                                DiagnosticCode::InvalidDirective,
+                                format!(
+                                    "{} cannot fit in a 16-bit word",
+                                    word.to_string().magenta()
+                                ),
+                            )
+                            .at(fname, value.place.clone()));
+                        }
+                        encoded_tokens.push(word as u16 as i16);
+                    }
+                }
+            },
+            _ => {}
+        }
+
+        Ok(encoded_tokens)
     }
+}
 
-    Ok(encoded_tokens)
+/// The words a string directive puts out, or the first character that
+/// doesn't fit in them
+///
+/// `.ascii` and `.asciiz` take a word per character, and `.asciiz` adds a zero
+/// word after them. `.packed` puts two 8-bit characters in each word, the
+/// first in the high byte, with a zero byte after an odd one out.
+pub fn string_words(directive: &str, text: &str) -> Result<Vec<i16>, char> {
+    if directive == "packed" {
+        let bytes = text
+            .chars()
+            .map(|c| u8::try_from(c).map_err(|_| c))
+            .collect::<Result<Vec<u8>, char>>()?;
+        return Ok(bytes
+            .chunks(2)
+            .map(|pair| i16::from_be_bytes([pair[0], pair.get(1).copied().unwrap_or(0)]))
+            .collect());
+    }
+    let mut words = text
+        .chars()
+        .map(|c| u16::try_from(c).map(|w| w as i16).map_err(|_| c))
+        .collect::<Result<Vec<i16>, char>>()?;
+    if directive == "asciiz" {
+        words.push(0);
+    }
+    Ok(words)
 }
@@ -1,4 +1,3 @@
 pub mod codegen_core;
 pub mod codegen_ins;
 pub use codegen_core::*;
-pub use codegen_ins::*;
@@ -1,7 +1,7 @@
-use clap::Parser;
-use once_cell::sync::Lazy;
-
-pub static CONFIG: Lazy<Args> = Lazy::new(declare_config);
+use crate::*;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, ValueEnum};
+use logos::Logos;
+use std::path::{Path, PathBuf};
 
 /// Command line arguments
 #[derive(Parser, Debug)]
@@ -18,30 +18,387 @@ pub struct Args {
     #[clap(required_unless_present = "repl")]
     pub source: Option<String>,
 
+    /// Write a listing of addresses, encodings and source lines to this file
+    #[clap(short = 'l', long, value_name = "FILE")]
+    pub listing: Option<String>,
+
+    /// Write a JSON map from every word's address to its source to this file
+    #[clap(short = 'g', long, value_name = "FILE")]
+    pub debug_info: Option<String>,
+
+    /// Write every label, constant and macro to this file
+    #[clap(long, value_name = "FILE")]
+    pub symbols: Option<String>,
+
+    /// Format of the --symbols file
+    #[clap(long, value_enum, default_value_t = SymbolFormat::Text)]
+    pub symbol_format: SymbolFormat,
+
     /// Verbose output
     #[clap(short = 'v', long, default_value_t = false)]
     pub verbose: bool,
 
-    /// Disable version and start information in binary
-    /// (may cause unexpected behavior in emulator and disassembler)
+    /// Leave out the header in binary output, the same as `--header none`
+    /// (the emulator and disassembler then can't tell where the code starts)
     #[clap(short = 't', long, default_value_t = false, verbatim_doc_comment)]
     pub thin: bool,
 
+    /// Layout of the header in front of binary output
+    #[clap(long, value_enum, default_value_t = HeaderFormat::V1)]
+    pub header: HeaderFormat,
+
     /// REPL mode
     #[clap(short = 'r', long, default_value_t = false, verbatim_doc_comment)]
     pub repl: bool,
+
+    /// Start address, overriding any .start directive
+    #[clap(short = 's', long, value_parser = parse_address)]
+    pub start: Option<i64>,
+
+    /// Output format
+    #[clap(short = 'f', long, value_enum, default_value_t = OutputFormat::Binary)]
+    pub format: OutputFormat,
+
+    /// Name of the array for c-array and rust-array output
+    /// (defaults to the name of the source file)
+    #[clap(long, value_name = "NAME", value_parser = parse_array_name, verbatim_doc_comment)]
+    pub array_name: Option<String>,
+
+    /// Additional directory to search for @include files (repeatable)
+    #[clap(short = 'I', long = "include")]
+    pub include_paths: Vec<PathBuf>,
+
+    /// What to do when an .incbin has an odd number of bytes
+    #[clap(long, value_enum, default_value_t = IncbinPadding::Zero)]
+    pub incbin_padding: IncbinPadding,
+
+    /// Warn about a lint, or `warnings` for all of them (repeatable)
+    #[clap(short = 'W', long = "warn", value_name = "LINT", value_parser = parse_lint_name)]
+    pub warn: Vec<String>,
+
+    /// Allow a lint, or `warnings` for all of them (repeatable)
+    #[clap(short = 'A', long = "allow", value_name = "LINT", value_parser = parse_lint_name)]
+    pub allow: Vec<String>,
+
+    /// Deny a lint, or `warnings` to turn every warning into an error. Anything
+    /// else defines a constant, as with --define (repeatable)
+    #[clap(short = 'D', long = "deny", value_name = "LINT|NAME[=EXPR]", value_parser = parse_deny, verbatim_doc_comment)]
+    pub deny: Vec<String>,
+
+    /// Define a constant before the source is read, as NAME=EXPR or NAME for
+    /// a value of 1 (repeatable)
+    #[clap(long, value_name = "NAME[=EXPR]", value_parser = parse_define, verbatim_doc_comment)]
+    pub define: Vec<(String, i64)>,
+
+    // --define and the -D flags that aren't lints, in the order given
+    #[clap(skip)]
+    pub constants: Vec<(String, i64)>,
+
+    /// Stop reporting errors after this many (0 for no limit)
+    #[clap(long, value_name = "N", default_value_t = 0)]
+    pub error_limit: usize,
+
+    /// How diagnostics are printed
+    #[clap(long, value_enum, default_value_t = MessageFormat::Human)]
+    pub message_format: MessageFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum MessageFormat {
+    /// Colored, with the offending source lines
+    #[default]
+    Human,
+    /// One JSON object per line
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum HeaderFormat {
+    /// No header, only the code
+    None,
+    /// The original header: magic, start address and metadata
+    #[default]
+    V1,
+    /// Versioned header with load address, code length and a CRC-32
+    V2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum IncbinPadding {
+    /// Fill the low byte of the last word with zero
+    #[default]
+    Zero,
+    /// Report an error
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum SymbolFormat {
+    /// One tab-separated symbol per line
+    #[default]
+    Text,
+    /// A single JSON object
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// Big-endian words, optionally preceded by a header
+    #[default]
+    #[value(name = "bin")]
+    Binary,
+    /// Intel HEX, with byte addresses
+    Ihex,
+    /// Motorola S-records, with byte addresses
+    Srec,
+    /// Relocatable object for basm-link
+    #[value(name = "obj")]
+    Object,
+    /// Hex words for Verilog's $readmemh
+    Memh,
+    /// Binary words for Verilog's $readmemb
+    Memb,
+    /// Logisim v2.0 raw memory image
+    Logisim,
+    /// C header with a uint16_t array
+    CArray,
+    /// Rust source with a [u16; N] constant
+    RustArray,
+}
+
+/// Command line arguments of `basm-link`
+#[derive(Parser, Debug)]
+#[command(name = "basm-link")]
+#[command(version = "0.5.0")]
+#[command(author = "gummi")]
+#[command(about = "The linker for BELLE object files", long_about = None)]
+pub struct LinkArgs {
+    /// Object files, placed in the order given
+    #[clap(required = true)]
+    pub objects: Vec<String>,
+
+    /// Output binary name
+    #[clap(short = 'o', long, default_value = "a.out")]
+    pub output: String,
+
+    /// Address the first object is placed at
+    #[clap(short = 'b', long, value_parser = parse_address, default_value = "100")]
+    pub base: i64,
+
+    /// Global label to start executing at (defaults to the base address)
+    #[clap(short = 'e', long)]
+    pub entry: Option<String>,
+
+    /// Output format
+    #[clap(short = 'f', long, value_enum, default_value_t = OutputFormat::Binary)]
+    pub format: OutputFormat,
+
+    /// Layout of the header in front of binary output
+    #[clap(long, value_enum, default_value_t = HeaderFormat::V1)]
+    pub header: HeaderFormat,
+
+    /// Name of the array for c-array and rust-array output
+    #[clap(long, value_name = "NAME", value_parser = parse_array_name)]
+    pub array_name: Option<String>,
+
+    /// Write every global symbol to this file
+    #[clap(long, value_name = "FILE")]
+    pub symbols: Option<String>,
+
+    /// Format of the --symbols file
+    #[clap(long, value_enum, default_value_t = SymbolFormat::Text)]
+    pub symbol_format: SymbolFormat,
+}
+
+impl From<&LinkArgs> for AssembleOptions {
+    fn from(args: &LinkArgs) -> Self {
+        AssembleOptions {
+            output_format: args.format,
+            header: args.header,
+            array_name: args
+                .array_name
+                .clone()
+                .unwrap_or_else(|| array_name_from_source(&args.output)),
+            ..AssembleOptions::default()
+        }
+    }
+}
+
+/// Options for a single assembly, independent of the command line
+#[derive(Debug, Clone, Default)]
+pub struct AssembleOptions {
+    pub verbose: bool,
+    pub header: HeaderFormat,
+    pub start_address: Option<i64>,
+    pub output_format: OutputFormat,
+    pub array_name: String,
+    // constants defined before the source is read
+    pub defines: Vec<(String, i64)>,
+    pub include_paths: Vec<PathBuf>,
+    pub incbin_padding: IncbinPadding,
+    pub lints: LintLevels,
+    pub error_limit: Option<usize>,
+}
+
+impl From<&Args> for AssembleOptions {
+    fn from(args: &Args) -> Self {
+        AssembleOptions {
+            verbose: args.verbose,
+            header: if args.thin {
+                HeaderFormat::None
+            } else {
+                args.header
+            },
+            start_address: args.start,
+            output_format: args.format,
+            array_name: args.array_name.clone().unwrap_or_else(|| {
+                array_name_from_source(args.source.as_deref().unwrap_or("program"))
+            }),
+            defines: args.constants.clone(),
+            include_paths: args.include_paths.clone(),
+            incbin_padding: args.incbin_padding,
+            lints: lint_levels(args),
+            error_limit: Some(args.error_limit).filter(|&limit| limit > 0),
+        }
+    }
+}
+
+// -A is applied first, then -W, then -D, so the strictest level wins
+fn lint_levels(args: &Args) -> LintLevels {
+    let mut lints = LintLevels::default();
+    for (names, level) in [
+        (&args.allow, LintLevel::Allow),
+        (&args.warn, LintLevel::Warn),
+        (&args.deny, LintLevel::Deny),
+    ] {
+        for name in names {
+            if level == LintLevel::Deny && is_define(name) {
+                continue;
+            }
+            match Lint::from_name(name) {
+                Some(lint) => lints.set(lint, level),
+                None if level == LintLevel::Deny => lints.deny_warnings = true,
+                None => lints.set_all(level),
+            }
+        }
+    }
+    lints
+}
+
+// --define and the -D flags that aren't lints, in the order they were given
+fn defines(args: &Args, matches: &ArgMatches) -> Vec<(String, i64)> {
+    let positions = |id| matches.indices_of(id).into_iter().flatten();
+    let mut defines: Vec<(usize, (String, i64))> = positions("define")
+        .zip(args.define.iter().cloned())
+        .collect();
+    defines.extend(
+        positions("deny")
+            .zip(&args.deny)
+            .filter(|(_, s)| is_define(s))
+            .filter_map(|(index, s)| Some((index, parse_define(s).ok()?))),
+    );
+    defines.sort_by_key(|(index, _)| *index);
+    defines.into_iter().map(|(_, define)| define).collect()
+}
+
+// lint names all have a hyphen in them, so they can't be mistaken for constants
+fn is_define(s: &str) -> bool {
+    s != "warnings" && Lint::from_name(s).is_none()
 }
 
 pub fn declare_config() -> Args {
-    let cli = Args::parse();
+    let matches = Args::command().get_matches();
+    let cli = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
-    let output = cli.output.unwrap_or_else(|| "a.out".to_string());
+    let output = cli.output.clone().unwrap_or_else(|| "a.out".to_string());
+    let constants = defines(&cli, &matches);
 
     Args {
-        source: cli.source,
         output: Some(output),
-        verbose: cli.verbose,
-        thin: cli.thin,
-        repl: cli.repl,
+        constants,
+        ..cli
+    }
+}
+
+fn parse_lint_name(s: &str) -> Result<String, String> {
+    if s == "warnings" || Lint::from_name(s).is_some() {
+        return Ok(s.to_string());
+    }
+    let names: Vec<&str> = Lint::ALL.iter().map(|lint| lint.name()).collect();
+    Err(format!(
+        "unknown lint `{s}`, expected `warnings` or one of: {}",
+        names.join(", ")
+    ))
+}
+
+fn parse_deny(s: &str) -> Result<String, String> {
+    // lint names have hyphens in them and constant names never do
+    if !is_define(s) || (s.contains('-') && !s.contains('=')) {
+        return parse_lint_name(s);
+    }
+    parse_define(s).map(|_| s.to_string())
+}
+
+// `NAME=EXPR`, where the expression can use literals and operators, or `NAME`
+// for 1
+fn parse_define(s: &str) -> Result<(String, i64), String> {
+    let (name, expr) = s.split_once('=').unwrap_or((s, "1"));
+    let mut chars = name.chars();
+    let valid_start = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+    if !valid_start || !chars.all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(format!("`{name}` is not a valid constant name"));
     }
+    let mut lexer = TokenKind::lexer(expr)
+        .spanned()
+        .collect::<Vec<_>>()
+        .into_iter()
+        .peekable();
+    let value = evaluate_expression(&COMMAND_LINE.to_string(), &mut lexer, &mut Assembler::new())
+        .map_err(|e| format!("invalid value for `{name}`: {}", e.message))?;
+    if lexer.next().is_some() {
+        return Err(format!(
+            "invalid value for `{name}`: `{expr}` is not one expression"
+        ));
+    }
+    Ok((name.to_string(), value))
+}
+
+fn parse_array_name(s: &str) -> Result<String, String> {
+    let mut chars = s.chars();
+    let valid_start = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+    if valid_start && chars.all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Ok(s.to_string());
+    }
+    Err(format!("`{s}` is not a valid C or Rust identifier"))
+}
+
+// `lib/fib-2.asm` becomes `fib_2`
+fn array_name_from_source(source: &str) -> String {
+    let stem = Path::new(source)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut name: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        name.insert(0, '_');
+    }
+    name
+}
+
+// accepts the same 0x/0o/0b prefixes as integer literals in source code
+pub fn parse_address(s: &str) -> Result<i64, String> {
+    let (digits, radix) = match s.get(..2) {
+        Some("0x") | Some("0X") => (&s[2..], 16),
+        Some("0o") | Some("0O") => (&s[2..], 8),
+        Some("0b") | Some("0B") => (&s[2..], 2),
+        _ => (s, 10),
+    };
+    i64::from_str_radix(digits, radix).map_err(|e| format!("invalid address `{s}`: {e}"))
 }
@@ -0,0 +1,82 @@
+use crate::*;
+use std::fmt;
+
+/// Stable identifiers for everything the assembler can report
+///
+/// The numbers are part of the public interface - new codes are appended,
+/// and existing ones are never renumbered or reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiagnosticCode {
+    InternalError,
+    InvalidToken,
+    ConstantSyntax,
+    DuplicateConstant,
+    InvalidExpression,
+    UnknownConstant,
+    InvalidInstruction,
+    MacroSyntax,
+    DuplicateMacro,
+    UnknownMacro,
+    MacroArguments,
+    DuplicateLabel,
+    UnknownLabel,
+    AddressOverflow,
+    InvalidDirective,
+    UnexpectedToken,
+    IncludeError,
+    EmptyMemory,
+    Io,
+    ErrorLimit,
+    UndefinedSymbol,
+    DuplicateSymbol,
+    RelocationOverflow,
+    InvalidObject,
+    DefineSyntax,
+    DuplicateDefine,
+    UnknownDefine,
+    IncludeCycle,
+    Lint(Lint),
+}
+
+impl DiagnosticCode {
+    pub fn as_str(&self) -> &'static str {
+        use DiagnosticCode::*;
+        match self {
+            InternalError => "E0000",
+            InvalidToken => "E0001",
+            ConstantSyntax => "E0002",
+            DuplicateConstant => "E0003",
+            InvalidExpression => "E0004",
+            UnknownConstant => "E0005",
+            InvalidInstruction => "E0006",
+            MacroSyntax => "E0007",
+            DuplicateMacro => "E0008",
+            UnknownMacro => "E0009",
+            MacroArguments => "E0010",
+            DuplicateLabel => "E0011",
+            UnknownLabel => "E0012",
+            AddressOverflow => "E0013",
+            InvalidDirective => "E0014",
+            UnexpectedToken => "E0015",
+            IncludeError => "E0016",
+            EmptyMemory => "E0017",
+            Io => "E0018",
+            ErrorLimit => "E0019",
+            UndefinedSymbol => "E0020",
+            DuplicateSymbol => "E0021",
+            RelocationOverflow => "E0022",
+            InvalidObject => "E0023",
+            DefineSyntax => "E0024",
+            DuplicateDefine => "E0025",
+            UnknownDefine => "E0026",
+            IncludeCycle => "E0027",
+            Lint(lint) => lint.code(),
+        }
+    }
+}
+
+impl fmt::Display for DiagnosticCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
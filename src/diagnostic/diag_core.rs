@@ -0,0 +1,130 @@
+use crate::*;
+use std::fmt;
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Note,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+/// A span of source text in `file`, optionally explained by `message`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub file: String,
+    pub span: Range<usize>,
+    pub message: Option<String>,
+}
+
+/// Something the assembler has to say about the program
+///
+/// Every stage produces these instead of printing, and the source text is
+/// only read back when a diagnostic is rendered. `primary` is the place the
+/// problem was found - it is absent for errors that aren't tied to source,
+/// like unreadable files. `secondary` points at related places, possibly in
+/// other files (previous declarations, similarly named symbols, the macro an
+/// instruction was expanded from).
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: DiagnosticCode,
+    pub message: String,
+    pub primary: Option<Box<Label>>,
+    pub secondary: Vec<Label>,
+    pub help: Option<String>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, code: DiagnosticCode, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity,
+            code,
+            message: message.into(),
+            primary: None,
+            secondary: Vec::new(),
+            help: None,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn error(code: DiagnosticCode, message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, code, message)
+    }
+
+    pub fn warning(lint: Lint, message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, DiagnosticCode::Lint(lint), message)
+    }
+
+    pub fn ice(location: String, message: String) -> Self {
+        Self::error(DiagnosticCode::InternalError, message)
+            .with_note(format!("internal compiler error in {location}"))
+            .with_note("this is a bug in the assembler, please report it")
+    }
+
+    /// Sets the primary span
+    pub fn at(mut self, file: &str, span: Range<usize>) -> Self {
+        self.primary = Some(Box::new(Label {
+            file: file.to_string(),
+            span,
+            message: None,
+        }));
+        self
+    }
+
+    pub fn with_label(mut self, file: &str, span: Range<usize>, message: Option<String>) -> Self {
+        self.secondary.push(Label {
+            file: file.to_string(),
+            span,
+            message,
+        });
+        self
+    }
+
+    pub fn with_labels(mut self, labels: Vec<Label>) -> Self {
+        self.secondary.extend(labels);
+        self
+    }
+
+    /// Points at where a constant was declared, or notes that it was defined
+    /// on the command line
+    pub fn with_declaration(self, file: &str, span: Range<usize>, message: &str) -> Self {
+        if file == COMMAND_LINE {
+            self.with_note("it is defined on the command line with -D")
+        } else {
+            self.with_label(file, span, Some(message.to_string()))
+        }
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    pub fn with_help_opt(mut self, help: Option<String>) -> Self {
+        if help.is_some() {
+            self.help = help;
+        }
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
@@ -0,0 +1,114 @@
+use crate::*;
+use std::fmt::Write;
+
+impl Diagnostic {
+    /// Renders the diagnostic as a single line of JSON
+    ///
+    /// Lines and columns are 1-based, columns count characters, and byte
+    /// ranges are 0-based and end-exclusive. Color codes are stripped from
+    /// every string.
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{");
+        let _ = write!(
+            out,
+            "\"severity\":{},\"code\":{},\"message\":{},\"span\":",
+            json_string(&self.severity.to_string()),
+            json_string(self.code.as_str()),
+            json_string(&self.message),
+        );
+        match &self.primary {
+            Some(label) => out.push_str(&span_json(label)),
+            None => out.push_str("null"),
+        }
+        let help = match &self.help {
+            Some(help) => json_string(help),
+            None => String::from("null"),
+        };
+        let notes: Vec<String> = self.notes.iter().map(|n| json_string(n)).collect();
+        let related: Vec<String> = self
+            .secondary
+            .iter()
+            .map(|label| {
+                let message = match &label.message {
+                    Some(message) => json_string(message),
+                    None => String::from("null"),
+                };
+                format!("{{\"span\":{},\"message\":{message}}}", span_json(label))
+            })
+            .collect();
+        let _ = write!(
+            out,
+            ",\"help\":{help},\"notes\":[{}],\"related\":[{}]}}",
+            notes.join(","),
+            related.join(",")
+        );
+        out
+    }
+}
+
+fn span_json(label: &Label) -> String {
+    let text = read_file(&label.file);
+    let position = |offset: usize| match line_column(&text, offset) {
+        Some((line, column)) => (line.to_string(), column.to_string()),
+        None => (String::from("null"), String::from("null")),
+    };
+    let (line_start, column_start) = position(label.span.start);
+    let (line_end, column_end) = position(label.span.end);
+    format!(
+        "{{\"file\":{},\"byte_start\":{},\"byte_end\":{},\"line_start\":{line_start},\"column_start\":{column_start},\"line_end\":{line_end},\"column_end\":{column_end}}}",
+        json_string(&label.file),
+        label.span.start,
+        label.span.end,
+    )
+}
+
+/// 1-based line and column of a byte offset, if it falls within the text
+pub fn line_column(text: &str, offset: usize) -> Option<(usize, usize)> {
+    if offset > text.len() || !text.is_char_boundary(offset) {
+        return None;
+    }
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    Some((line, before[line_start..].chars().count() + 1))
+}
+
+/// Quotes and escapes `s` as a JSON string, without its color codes
+pub fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in strip_ansi(s).chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Removes the terminal escape sequences `colored` wraps text in
+pub fn strip_ansi(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' && chars.peek() == Some(&'[') {
+            chars.next();
+            // parameters and intermediates, up to and including the final byte
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
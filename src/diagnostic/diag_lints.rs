@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::fmt;
+
+/// A named warning that can be allowed, warned about, or denied
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    UnusedLabel,
+    UnusedConstant,
+    UnusedMacro,
+    UnreachableCode,
+    ZeroPad,
+    ImmediateTruncation,
+    LabelShadowsConstant,
+    EmptyProgram,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 8] = [
+        Lint::UnusedLabel,
+        Lint::UnusedConstant,
+        Lint::UnusedMacro,
+        Lint::UnreachableCode,
+        Lint::ZeroPad,
+        Lint::ImmediateTruncation,
+        Lint::LabelShadowsConstant,
+        Lint::EmptyProgram,
+    ];
+
+    /// The name used to refer to this lint on the command line
+    pub fn name(&self) -> &'static str {
+        use Lint::*;
+        match self {
+            UnusedLabel => "unused-label",
+            UnusedConstant => "unused-constant",
+            UnusedMacro => "unused-macro",
+            UnreachableCode => "unreachable-code",
+            ZeroPad => "zero-pad",
+            ImmediateTruncation => "imm-truncation",
+            LabelShadowsConstant => "label-shadows-constant",
+            EmptyProgram => "empty-program",
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        use Lint::*;
+        match self {
+            UnusedLabel => "W0001",
+            UnusedConstant => "W0002",
+            UnusedMacro => "W0003",
+            UnreachableCode => "W0004",
+            ZeroPad => "W0005",
+            ImmediateTruncation => "W0006",
+            LabelShadowsConstant => "W0007",
+            EmptyProgram => "W0008",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Lint> {
+        Lint::ALL.into_iter().find(|lint| lint.name() == name)
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// The level every lint is reported at
+///
+/// Lints that were never mentioned are warnings. `deny_warnings` (`-D warnings`)
+/// turns every lint that would warn into an error.
+#[derive(Debug, Clone, Default)]
+pub struct LintLevels {
+    levels: HashMap<Lint, LintLevel>,
+    pub deny_warnings: bool,
+}
+
+impl LintLevels {
+    pub fn set(&mut self, lint: Lint, level: LintLevel) {
+        self.levels.insert(lint, level);
+    }
+
+    pub fn set_all(&mut self, level: LintLevel) {
+        for lint in Lint::ALL {
+            self.set(lint, level);
+        }
+    }
+
+    pub fn level(&self, lint: Lint) -> LintLevel {
+        match self.levels.get(&lint) {
+            Some(LintLevel::Warn) | None if self.deny_warnings => LintLevel::Deny,
+            Some(level) => *level,
+            None => LintLevel::Warn,
+        }
+    }
+
+    /// Whether the level was picked on the command line rather than by default
+    pub fn is_explicit(&self, lint: Lint) -> bool {
+        self.levels.contains_key(&lint)
+    }
+}
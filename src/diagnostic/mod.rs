@@ -0,0 +1,8 @@
+pub mod diag_codes;
+pub mod diag_core;
+pub mod diag_json;
+pub mod diag_lints;
+pub use diag_codes::*;
+pub use diag_core::*;
+pub use diag_json::*;
+pub use diag_lints::*;
@@ -1,8 +1,112 @@
+use crate::*;
 use colored::*;
 use std::fmt;
 use std::ops::Range;
 use term_size::dimensions;
 
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let title = format!("{}[{}]", self.severity, self.code);
+        // without an explicit help message, the first related location explains
+        // itself on the note line, and the rest of the list hangs off of it
+        let first_message = self.secondary.first().and_then(|l| l.message.clone());
+        let summary = self.help.clone().or_else(|| first_message.clone());
+        let summary = if self.secondary.is_empty() {
+            summary
+        } else {
+            Some(format!(
+                "{} {}",
+                "╮".bright_red(),
+                summary.unwrap_or_default()
+            ))
+        };
+
+        let mut rendered = false;
+        if let Some(label) = &self.primary {
+            let input = read_file(&label.file);
+            if label.span.start < input.len() && label.span.end <= input.len() {
+                let lines: Vec<&str> = input.lines().collect();
+                print_err_and_line(
+                    f,
+                    0,
+                    (
+                        &title,
+                        &input,
+                        &self.message,
+                        &summary,
+                        &label.file,
+                        &label.span,
+                    ),
+                    lines,
+                )?;
+                rendered = true;
+            }
+        }
+        if !rendered {
+            // nothing to point at, so only the message (and file) is shown
+            let colored_title = if self.is_error() {
+                title.bright_red().underline()
+            } else {
+                title.yellow().underline()
+            };
+            write!(f, "{colored_title}: {}", self.message)?;
+            let left_char = if summary.is_some() { "├" } else { "╰" };
+            if let Some(label) = &self.primary {
+                write!(
+                    f,
+                    "\n{}{} {}",
+                    left_char.bright_red(),
+                    "─".bright_red(),
+                    label.file.green()
+                )?;
+            }
+            if let Some(s) = &summary {
+                write!(
+                    f,
+                    "\n{}{} {}: {s}",
+                    "╰".bright_red(),
+                    ">".yellow(),
+                    "note".yellow()
+                )?;
+            }
+        }
+
+        let size = self.secondary.len().saturating_sub(1);
+        let max_filename_length = self
+            .secondary
+            .iter()
+            .map(|l| l.file.len())
+            .max()
+            .unwrap_or(0);
+        for (index, label) in self.secondary.iter().enumerate() {
+            let (l_num, data) = highlight_range_in_file(&label.file, &label.span);
+            let connector = if index != size { "├" } else { "╰" };
+            write!(
+                f,
+                "\n         {}{} in {:<width$} {}{} {:^6} {} {}",
+                connector.bright_red(),
+                ">".yellow(),
+                label.file.green(),
+                "-".bright_red(),
+                ">".yellow(),
+                l_num.to_string().blue(),
+                "│".blue(),
+                data,
+                width = max_filename_length,
+            )?;
+            let shown_above = index == 0 && self.help.is_none();
+            if let (Some(message), false) = (&label.message, shown_above) {
+                write!(f, " {}", message.dimmed())?;
+            }
+        }
+
+        for note in &self.notes {
+            write!(f, "\n   {} {}: {note}", "=".blue(), "note".bold())?;
+        }
+        Ok(())
+    }
+}
+
 pub fn print_err_and_line(
     f: &mut fmt::Formatter<'_>,
     indents: usize,
@@ -46,7 +150,7 @@ pub fn print_err_and_line(
                     );
                 }
             }
-            if title == "error" {
+            if title.starts_with("error") {
                 writeln!(
                     f,
                     "{start_spaces}{}: {}",
@@ -1,24 +1,27 @@
 use crate::*;
 use std::iter::Peekable;
-type Evalex<'a> = Peekable<logos::SpannedIter<'a, tokens::TokenKind>>;
+use std::ops::Range;
+
+/// The tokens of a line, as expressions are read from them
+pub type Evalex = Peekable<std::vec::IntoIter<(Result<TokenKind, ()>, Range<usize>)>>;
 
 pub fn parse_expression(
     file: &String,
-    input: &String,
     token_iter: &mut Evalex,
-) -> Result<Expr, ParserError> {
+    asm: &mut Assembler,
+) -> Result<Expr, Diagnostic> {
     /*if let Ok(ref d) = v {
         let e = d.evaluate();
         println!("{e}\n{d}");
     }*/
-    parse_bitwise(file, input, token_iter)
+    parse_bitwise(file, token_iter, asm)
 }
 
 pub fn parse_primary(
     file: &String,
-    input: &String,
     token_iter: &mut Evalex,
-) -> Result<Expr, ParserError> {
+    asm: &mut Assembler,
+) -> Result<Expr, Diagnostic> {
     let mut last_loc = 0..0;
 
     if let Some((_, loc)) = token_iter.peek() {
@@ -30,92 +33,91 @@ pub fn parse_primary(
         match token {
             Ok(TokenKind::IntLit(num)) => Ok(Expr::Int(num)),
             Ok(TokenKind::LeftParen) => {
-                let expr = parse_expression(file, input, token_iter)?;
+                let expr = parse_expression(file, token_iter, asm)?;
                 if let Some((Ok(TokenKind::RightParen), _)) = token_iter.next() {
                     Ok(expr)
                 } else {
-                    Err(ParserError {
-                        file: file.to_string(),
-                        help: Some(String::from("close this parenthesis")),
-                        input: input.to_string(),
-                        message: "unmatched parenthesis".to_string(),
-                        start_pos: last_loc.start,
-                        last_pos: last_loc.end,
-                    })
+                    Err(Diagnostic::error(
+                        DiagnosticCode::InvalidExpression,
+                        "unmatched parenthesis".to_string(),
+                    )
+                    .at(file, last_loc.clone())
+                    .with_help(String::from("close this parenthesis")))
                 }
             }
             Ok(TokenKind::Ident(val)) => {
-                let vmap = V_MAP.lock().unwrap();
-                if let Some((_, _, v)) = vmap.get(&val) {
-                    let val = Ok(Expr::Int(*v));
-                    std::mem::drop(vmap);
-                    val
+                if let Some((_, _, v)) = asm.v_map.get(&val) {
+                    let v = *v;
+                    asm.uses.constants.insert(val);
+                    Ok(Expr::Int(v))
                 } else {
-                    Err(ParserError {
-                        file: file.to_string(),
-                        help: None,
-                        input: input.to_string(),
-                        message: format!("constant with name {val} not found"),
-                        start_pos: last_loc.start,
-                        last_pos: last_loc.end,
-                    })
+                    Err(Diagnostic::error(
+                        DiagnosticCode::UnknownConstant,
+                        format!("constant with name {val} not found"),
+                    )
+                    .at(file, last_loc.clone()))
                 }
             }
-            Ok(v) => Err(ParserError {
-                file: file.to_string(),
-                help: Some(String::from(
-                    "valid expressions allow operators and constant names",
-                )),
-                input: input.to_string(),
-                message: format!("unexpected {v} in expression"),
-                start_pos: last_loc.start,
-                last_pos: last_loc.end,
-            }),
-            _ => Err(ParserError {
-                file: file.to_string(),
-                help: Some(String::from("there is likely an invalid character")),
-                input: input.to_string(),
-                message: String::from(
+            Ok(v) => Err(Diagnostic::error(
+                DiagnosticCode::InvalidExpression,
+                format!("unexpected {v} in expression"),
+            )
+            .at(file, last_loc.clone())
+            .with_help(String::from(
+                "valid expressions allow operators and constant names",
+            ))),
+            _ => Err(Diagnostic::error(
+                DiagnosticCode::InvalidExpression,
+                String::from(
                     "reached an error while parsing expression\nmaybe the expression is invalid?",
                 ),
-                start_pos: last_loc.start,
-                last_pos: last_loc.end,
-            }),
+            )
+            .at(file, last_loc.clone())
+            .with_help(String::from("there is likely an invalid character"))),
         }
     } else {
-        Err(ParserError {
-            file: file.to_string(),
-            help: Some(String::from(
-                "could not continue iterating over tokens here",
-            )),
-            input: input.to_string(),
-            message: "unexpected end of expression".to_string(),
-            start_pos: last_loc.start,
-            last_pos: last_loc.end,
-        })
+        Err(Diagnostic::error(
+            DiagnosticCode::InvalidExpression,
+            "unexpected end of expression".to_string(),
+        )
+        .at(file, last_loc.clone())
+        .with_help(String::from(
+            "could not continue iterating over tokens here",
+        )))
     }
 }
 
 pub fn evaluate_expression(
     file: &String,
-    input: &String,
     token_iter: &mut Evalex,
-) -> Result<i64, ParserError> {
-    let expr = parse_expression(file, input, token_iter)?;
-    if CONFIG.verbose {
+    asm: &mut Assembler,
+) -> Result<i64, Diagnostic> {
+    // the tokens the expression is made of, for an error in its value to point at
+    let before = token_iter.clone();
+    let expr = parse_expression(file, token_iter, asm)?;
+    let value = expr.evaluate();
+    if asm.options.verbose {
         print_msg!("BEGINNING AST EXPRESSION EVALUATION\n\nRAW EXPR:\n{expr:?}");
         println!();
         print_msg!("CONSTRUCTED AST");
-        println!("{}", expr.evaluate());
+        println!("{value:?}");
         println!("{expr}");
     }
-    Ok(expr.evaluate())
+    value.map_err(|message| {
+        let used = before.len() - token_iter.len();
+        let mut spans = before.take(used).map(|(_, span)| span);
+        let first = spans.next().unwrap_or(0..0);
+        let last = spans.next_back().unwrap_or(first.clone());
+        Diagnostic::error(DiagnosticCode::InvalidExpression, message)
+            .at(file, first.start..last.end)
+            .with_help("expressions are worked out with 64-bit integers")
+    })
 }
 pub fn parse_expression_after_left_paren(
     file: &str,
-    input: &String,
-    lexer: &mut std::iter::Peekable<logos::SpannedIter<'_, TokenKind>>,
-) -> Result<Option<(i64, logos::Span)>, ParserError> {
+    lexer: &mut Evalex,
+    asm: &mut Assembler,
+) -> Result<Option<(i64, logos::Span)>, Diagnostic> {
     let mut peek_iter = lexer.clone();
     while let Some((peek_token, _)) = peek_iter.peek() {
         match peek_token {
@@ -132,30 +134,26 @@ pub fn parse_expression_after_left_paren(
     let next_token = lexer.peek().cloned();
     match next_token {
         Some((Ok(_), span)) => {
-            let value = evaluate_expression(&file.to_string(), input, lexer)?;
+            let value = evaluate_expression(&file.to_string(), lexer, asm)?;
             return Ok(Some((value, span.clone())));
         }
         Some((Err(_), span)) => {
-            return Err(ParserError {
-                file: file.to_string(),
-                help: Some(String::from(
-                    "valid characters are math symbols and constant names",
-                )),
-                input: read_file(file),
-                message: String::from("invalid character in expression"),
-                start_pos: span.start,
-                last_pos: span.end,
-            });
+            return Err(Diagnostic::error(
+                DiagnosticCode::InvalidExpression,
+                String::from("invalid character in expression"),
+            )
+            .at(file, span.clone())
+            .with_help(String::from(
+                "valid characters are math symbols and constant names",
+            )));
         }
         None => {}
     }
 
-    Err(ParserError {
-        file: file.to_string(),
-        help: Some(String::from("expression might be empty")),
-        input: read_file(file),
-        message: String::from("failed to parse expression"),
-        start_pos: 0,
-        last_pos: 0,
-    })
+    Err(Diagnostic::error(
+        DiagnosticCode::InvalidExpression,
+        String::from("failed to parse expression"),
+    )
+    .at(file, 0..0)
+    .with_help(String::from("expression might be empty")))
 }
@@ -13,18 +13,42 @@ pub enum Expr {
 }
 
 impl Expr {
-    pub fn evaluate(&self) -> i64 {
-        match self {
+    /// The value of the expression, or what made it go outside 64 bits
+    pub fn evaluate(&self) -> Result<i64, String> {
+        let overflow =
+            |op: &str, lhs: i64, rhs: i64| format!("`{lhs} {op} {rhs}` does not fit in 64 bits");
+        let shift = |op: &str, lhs: i64, rhs: i64| {
+            u32::try_from(rhs)
+                .ok()
+                .and_then(|rhs| match op {
+                    "<<" => lhs.checked_shl(rhs),
+                    _ => lhs.checked_shr(rhs),
+                })
+                .ok_or_else(|| format!("cannot shift by {rhs}, shifts go from 0 to 63"))
+        };
+        Ok(match self {
             Expr::Int(n) => *n,
-            Expr::Add(lhs, rhs) => lhs.evaluate() + rhs.evaluate(),
-            Expr::Sub(lhs, rhs) => lhs.evaluate() - rhs.evaluate(),
-            Expr::Mul(lhs, rhs) => lhs.evaluate() * rhs.evaluate(),
-            Expr::Shl(lhs, rhs) => lhs.evaluate() << rhs.evaluate() as u32,
-            Expr::Shr(lhs, rhs) => lhs.evaluate() >> rhs.evaluate() as u32,
-            Expr::BitAnd(lhs, rhs) => lhs.evaluate() & rhs.evaluate(),
-            Expr::BitOr(lhs, rhs) => lhs.evaluate() | rhs.evaluate(),
-            Expr::Xor(lhs, rhs) => lhs.evaluate() ^ rhs.evaluate(),
-        }
+            Expr::Add(lhs, rhs) => {
+                let (lhs, rhs) = (lhs.evaluate()?, rhs.evaluate()?);
+                lhs.checked_add(rhs)
+                    .ok_or_else(|| overflow("+", lhs, rhs))?
+            }
+            Expr::Sub(lhs, rhs) => {
+                let (lhs, rhs) = (lhs.evaluate()?, rhs.evaluate()?);
+                lhs.checked_sub(rhs)
+                    .ok_or_else(|| overflow("-", lhs, rhs))?
+            }
+            Expr::Mul(lhs, rhs) => {
+                let (lhs, rhs) = (lhs.evaluate()?, rhs.evaluate()?);
+                lhs.checked_mul(rhs)
+                    .ok_or_else(|| overflow("*", lhs, rhs))?
+            }
+            Expr::Shl(lhs, rhs) => shift("<<", lhs.evaluate()?, rhs.evaluate()?)?,
+            Expr::Shr(lhs, rhs) => shift(">>", lhs.evaluate()?, rhs.evaluate()?)?,
+            Expr::BitAnd(lhs, rhs) => lhs.evaluate()? & rhs.evaluate()?,
+            Expr::BitOr(lhs, rhs) => lhs.evaluate()? | rhs.evaluate()?,
+            Expr::Xor(lhs, rhs) => lhs.evaluate()? ^ rhs.evaluate()?,
+        })
     }
 }
 impl fmt::Display for Expr {
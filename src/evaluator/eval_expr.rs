@@ -1,14 +1,11 @@
 use crate::*;
-use std::iter::Peekable;
-
-type Evalex<'a> = Peekable<logos::SpannedIter<'a, tokens::TokenKind>>;
 
 pub fn parse_bitwise(
     file: &String,
-    input: &String,
     token_iter: &mut Evalex,
-) -> Result<Expr, ParserError> {
-    let mut expr = parse_add_sub(file, input, token_iter)?;
+    asm: &mut Assembler,
+) -> Result<Expr, Diagnostic> {
+    let mut expr = parse_add_sub(file, token_iter, asm)?;
 
     while let Some((token, _)) = token_iter.peek() {
         match token {
@@ -16,21 +13,21 @@ pub fn parse_bitwise(
                 token_iter.next();
                 expr = Expr::BitAnd(
                     Box::new(expr),
-                    Box::new(parse_add_sub(file, input, token_iter)?),
+                    Box::new(parse_add_sub(file, token_iter, asm)?),
                 );
             }
             Ok(TokenKind::Pipe) => {
                 token_iter.next();
                 expr = Expr::BitOr(
                     Box::new(expr),
-                    Box::new(parse_add_sub(file, input, token_iter)?),
+                    Box::new(parse_add_sub(file, token_iter, asm)?),
                 );
             }
             Ok(TokenKind::Xor) => {
                 token_iter.next();
                 expr = Expr::Xor(
                     Box::new(expr),
-                    Box::new(parse_add_sub(file, input, token_iter)?),
+                    Box::new(parse_add_sub(file, token_iter, asm)?),
                 );
             }
             _ => break,
@@ -41,10 +38,10 @@ pub fn parse_bitwise(
 
 pub fn parse_add_sub(
     file: &String,
-    input: &String,
     token_iter: &mut Evalex,
-) -> Result<Expr, ParserError> {
-    let mut expr = parse_mul_shift(file, input, token_iter)?;
+    asm: &mut Assembler,
+) -> Result<Expr, Diagnostic> {
+    let mut expr = parse_mul_shift(file, token_iter, asm)?;
 
     while let Some((token, _)) = token_iter.peek() {
         match token {
@@ -52,14 +49,14 @@ pub fn parse_add_sub(
                 token_iter.next();
                 expr = Expr::Add(
                     Box::new(expr),
-                    Box::new(parse_mul_shift(file, input, token_iter)?),
+                    Box::new(parse_mul_shift(file, token_iter, asm)?),
                 );
             }
             Ok(TokenKind::Minus) => {
                 token_iter.next();
                 expr = Expr::Sub(
                     Box::new(expr),
-                    Box::new(parse_mul_shift(file, input, token_iter)?),
+                    Box::new(parse_mul_shift(file, token_iter, asm)?),
                 );
             }
             _ => break,
@@ -70,10 +67,10 @@ pub fn parse_add_sub(
 
 pub fn parse_mul_shift(
     file: &String,
-    input: &String,
     token_iter: &mut Evalex,
-) -> Result<Expr, ParserError> {
-    let mut expr = parse_primary(file, input, token_iter)?;
+    asm: &mut Assembler,
+) -> Result<Expr, Diagnostic> {
+    let mut expr = parse_primary(file, token_iter, asm)?;
 
     while let Some((token, _)) = token_iter.peek() {
         match token {
@@ -81,21 +78,21 @@ pub fn parse_mul_shift(
                 token_iter.next();
                 expr = Expr::Mul(
                     Box::new(expr),
-                    Box::new(parse_primary(file, input, token_iter)?),
+                    Box::new(parse_primary(file, token_iter, asm)?),
                 );
             }
             Ok(TokenKind::LessLess) => {
                 token_iter.next();
                 expr = Expr::Shl(
                     Box::new(expr),
-                    Box::new(parse_primary(file, input, token_iter)?),
+                    Box::new(parse_primary(file, token_iter, asm)?),
                 );
             }
             Ok(TokenKind::GreaterGreater) => {
                 token_iter.next();
                 expr = Expr::Shr(
                     Box::new(expr),
-                    Box::new(parse_primary(file, input, token_iter)?),
+                    Box::new(parse_primary(file, token_iter, asm)?),
                 );
             }
             _ => break,
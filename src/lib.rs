@@ -1,20 +1,85 @@
+pub mod assembler;
 pub mod codegen;
 pub mod config;
+pub mod diagnostic;
 pub mod err_misc;
 pub mod evaluator;
+pub mod linker;
 pub mod misc;
+pub mod output;
 pub mod parser;
 pub mod preproc;
 pub mod symbol_table;
 pub mod tokens;
 pub mod validator;
+pub use assembler::*;
 pub use codegen::*;
 pub use config::*;
+pub use diagnostic::*;
 pub use err_misc::*;
 pub use evaluator::*;
+pub use linker::*;
 pub use misc::*;
+pub use output::*;
 pub use parser::*;
 pub use preproc::*;
 pub use symbol_table::*;
 pub use tokens::*;
 pub use validator::*;
+
+/// Assembles the program whose entry point is the file at `source`
+///
+/// Nothing here prints errors or exits the process: every problem, including
+/// internal compiler errors, is returned to the caller. Warnings come back
+/// alongside the errors on failure, and in `warnings` on success.
+pub fn assemble(
+    source: &str,
+    options: &AssembleOptions,
+) -> Result<AssembledProgram, Vec<Diagnostic>> {
+    let input = load_file(source).map_err(|e| vec![e])?;
+    let mut asm = Assembler::with_options(options.clone());
+    let binary = asm.assemble_source(source, &input);
+    asm.lint_unused();
+    if binary.is_empty() {
+        asm.lint(
+            Lint::EmptyProgram,
+            Diagnostic::warning(Lint::EmptyProgram, format!("{source} appears empty")),
+        );
+    }
+
+    if matches!(
+        options.output_format,
+        OutputFormat::CArray | OutputFormat::RustArray
+    ) {
+        let clashes = array_name_clashes(&asm.label_map, &options.array_name);
+        asm.diagnostics.extend(clashes);
+    }
+
+    asm.apply_error_limit();
+
+    if asm.diagnostics.iter().any(|d| d.is_error()) {
+        return Err(asm.diagnostics);
+    }
+
+    if options.verbose {
+        print_msg!("SYMBOL TABLES");
+        asm.print_symbol_tables();
+    }
+
+    Ok(AssembledProgram {
+        source: source.to_string(),
+        binary,
+        origins: asm.origins,
+        gaps: asm.gaps,
+        start_location: asm.start_location,
+        entry_point: asm.start_location,
+        metadata_str: asm.metadata_str,
+        v_map: asm.v_map,
+        label_map: asm.label_map,
+        macro_map: asm.macro_map,
+        globals: asm.globals,
+        includes: asm.includes,
+        relocations: asm.relocations,
+        warnings: asm.diagnostics,
+    })
+}
@@ -0,0 +1,149 @@
+use crate::*;
+use colored::*;
+use std::collections::HashMap;
+
+/// Links `objects`, given with the names they were read from, into one program
+///
+/// The objects are placed one after another from `base`, in the order given.
+/// Global symbols are visible to every object, and the rest only to the
+/// object that defines them. The entry point is the global symbol `entry`,
+/// or `base` when there is none.
+pub fn link(
+    objects: &[(String, ObjectFile)],
+    base: u16,
+    entry: Option<&str>,
+) -> Result<AssembledProgram, Vec<Diagnostic>> {
+    let mut errors = Vec::new();
+
+    let mut places = Vec::new();
+    let mut next = base as usize;
+    for (_, object) in objects {
+        places.push(next);
+        next += object.code.len();
+    }
+
+    // name -> (object, address)
+    let mut globals: HashMap<&str, (usize, usize)> = HashMap::new();
+    for (index, (name, object)) in objects.iter().enumerate() {
+        for sym in object
+            .symbols
+            .iter()
+            .filter(|sym| sym.defined && sym.global)
+        {
+            let address = places[index] + sym.value as usize;
+            if let Some((other, _)) = globals.insert(&sym.name, (index, address)) {
+                errors.push(
+                    Diagnostic::error(
+                        DiagnosticCode::DuplicateSymbol,
+                        format!(
+                            "symbol `{}` is defined in both {} and {name}",
+                            sym.name.magenta(),
+                            objects[other].0
+                        ),
+                    )
+                    .with_help("only one object can declare a label `.global`"),
+                );
+            }
+        }
+    }
+
+    let mut binary = Vec::new();
+    for (index, (name, object)) in objects.iter().enumerate() {
+        let mut code = object.code.clone();
+        for reloc in &object.relocations {
+            let (Some(sym), Some(word)) = (
+                object.symbols.get(reloc.symbol as usize),
+                code.get_mut(reloc.offset as usize),
+            ) else {
+                errors.push(Diagnostic::error(
+                    DiagnosticCode::InvalidObject,
+                    format!("{name} has a relocation outside of its code or symbols"),
+                ));
+                continue;
+            };
+            let address = if sym.defined {
+                places[index] + sym.value as usize
+            } else if let Some((_, address)) = globals.get(sym.name.as_str()) {
+                *address
+            } else {
+                errors.push(
+                    Diagnostic::error(
+                        DiagnosticCode::UndefinedSymbol,
+                        format!(
+                            "undefined symbol `{}` referenced in {name}",
+                            sym.name.magenta()
+                        ),
+                    )
+                    .with_help("declare the label `.global` in the object that defines it"),
+                );
+                continue;
+            };
+            match reloc.kind.apply(*word, address) {
+                Some(patched) => *word = patched,
+                None => errors.push(
+                    Diagnostic::error(
+                        DiagnosticCode::RelocationOverflow,
+                        format!(
+                            "the address of `{}` cannot fit within {} bits",
+                            sym.name.magenta(),
+                            reloc.kind.bits()
+                        ),
+                    )
+                    .with_help(format!(
+                        "it was placed at 0x{address:X}, referenced from word {} of {name}",
+                        reloc.offset
+                    )),
+                ),
+            }
+        }
+        binary.extend(code);
+    }
+
+    let entry_point = match entry {
+        Some(symbol) => match globals.get(symbol) {
+            Some((_, address)) => *address as i64,
+            None => {
+                errors.push(Diagnostic::error(
+                    DiagnosticCode::UndefinedSymbol,
+                    format!("entry point `{}` is not a global symbol", symbol.magenta()),
+                ));
+                base as i64
+            }
+        },
+        None => base as i64,
+    };
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let label_map = globals
+        .into_iter()
+        .map(|(sym, (index, address))| {
+            let file = objects[index].0.clone();
+            (sym.to_string(), (file, 0..0, address))
+        })
+        .collect();
+    Ok(AssembledProgram {
+        source: objects
+            .first()
+            .map(|(name, _)| name.clone())
+            .unwrap_or_default(),
+        binary,
+        origins: Vec::new(),
+        gaps: Vec::new(),
+        start_location: base as i64,
+        entry_point,
+        metadata_str: objects
+            .iter()
+            .map(|(_, object)| object.metadata.as_str())
+            .collect(),
+        v_map: ConstMap::new(),
+        label_map,
+        macro_map: MacroMap::new(),
+        relocations: Vec::new(),
+        globals: SymbolTable::new(),
+        includes: SymbolTable::new(),
+        warnings: Vec::new(),
+    })
+}
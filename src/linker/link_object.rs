@@ -0,0 +1,317 @@
+use crate::*;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Magic bytes at the start of an object file
+pub const OBJECT_MAGIC: [u8; 4] = *b"BOBJ";
+pub const OBJECT_VERSION: u16 = 1;
+
+/// An address field the linker fills in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelocationKind {
+    /// Bits 0-9 of a branch (`jmp`, `bz`, ...)
+    Branch10,
+    /// Bits 0-8 of `ld` and `lea`
+    Load9,
+    /// Bits 3-10 of `st`
+    Store8,
+    /// Bits 0-10 of `pop`
+    Pop11,
+    /// The whole word, for `.word`
+    Word16,
+}
+
+impl RelocationKind {
+    /// The field an instruction puts a label's address in, if it takes one
+    pub fn for_instruction(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "bo" | "bno" | "bl" | "bg" | "jmp" | "bz" | "bnz" => Some(RelocationKind::Branch10),
+            "ld" | "lea" => Some(RelocationKind::Load9),
+            "st" => Some(RelocationKind::Store8),
+            "pop" => Some(RelocationKind::Pop11),
+            _ => None,
+        }
+    }
+
+    pub fn bits(&self) -> u32 {
+        match self {
+            RelocationKind::Branch10 => 10,
+            RelocationKind::Load9 => 9,
+            RelocationKind::Store8 => 8,
+            RelocationKind::Pop11 => 11,
+            RelocationKind::Word16 => 16,
+        }
+    }
+
+    fn shift(&self) -> u32 {
+        match self {
+            RelocationKind::Store8 => 3,
+            _ => 0,
+        }
+    }
+
+    /// `word` with `address` in its field, or `None` if the address doesn't fit
+    pub fn apply(&self, word: i16, address: usize) -> Option<i16> {
+        // a .word holds the amount to add to the address, as in `.word table + 2`
+        if *self == RelocationKind::Word16 {
+            let value = word as i64 + address as i64;
+            return (i16::MIN as i64..=u16::MAX as i64)
+                .contains(&value)
+                .then_some(value as u16 as i16);
+        }
+        if address >= 1 << self.bits() {
+            return None;
+        }
+        let mask = ((1u32 << self.bits()) - 1) << self.shift();
+        let patched = (word as u16 as u32 & !mask) | ((address as u32) << self.shift());
+        Some(patched as u16 as i16)
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            RelocationKind::Branch10 => 0,
+            RelocationKind::Load9 => 1,
+            RelocationKind::Store8 => 2,
+            RelocationKind::Pop11 => 3,
+            RelocationKind::Word16 => 4,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(RelocationKind::Branch10),
+            1 => Some(RelocationKind::Load9),
+            2 => Some(RelocationKind::Store8),
+            3 => Some(RelocationKind::Pop11),
+            4 => Some(RelocationKind::Word16),
+            _ => None,
+        }
+    }
+}
+
+/// A word whose address field refers to `symbol`, recorded while assembling
+/// an object
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relocation {
+    // index of the word in the program
+    pub offset: usize,
+    pub kind: RelocationKind,
+    pub symbol: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectSymbol {
+    pub name: String,
+    // offset in words from the start of the object, 0 when undefined
+    pub value: u16,
+    pub defined: bool,
+    pub global: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectRelocation {
+    pub offset: u16,
+    pub kind: RelocationKind,
+    // index into the symbols of the same object
+    pub symbol: u16,
+}
+
+/// A relocatable object, as written by `-f obj` and read by `basm-link`
+///
+/// The layout is big-endian:
+///
+/// | size | field |
+/// | :--- | :---- |
+/// | 4 | magic, `BOBJ` |
+/// | 2 | format version, `1` |
+/// | 2 | code length in words (n) |
+/// | 2 | symbol count (s) |
+/// | 2 | relocation count (r) |
+/// | 2n | code |
+/// | s × | flags (bit 0 defined, bit 1 global), name length (2 bytes), name, value (2 bytes) |
+/// | r × | word offset (2 bytes), kind, symbol index (2 bytes) |
+/// | 2 + m | metadata length, metadata |
+///
+/// Address fields that refer to a symbol are left as zero in the code, for
+/// the linker to fill in. A `.word` keeps the amount added to the address
+/// instead, and the linker adds the address to it.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ObjectFile {
+    pub code: Vec<i16>,
+    pub symbols: Vec<ObjectSymbol>,
+    pub relocations: Vec<ObjectRelocation>,
+    pub metadata: String,
+}
+
+/// Why an object file couldn't be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ObjectError {
+    Truncated,
+    BadMagic,
+    UnsupportedVersion(u16),
+    BadRelocationKind(u8),
+}
+
+impl fmt::Display for ObjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjectError::Truncated => write!(f, "the object file is cut short"),
+            ObjectError::BadMagic => write!(f, "not a BELLE object file"),
+            ObjectError::UnsupportedVersion(v) => write!(f, "unsupported object version {v}"),
+            ObjectError::BadRelocationKind(k) => write!(f, "unknown relocation kind {k}"),
+        }
+    }
+}
+
+impl std::error::Error for ObjectError {}
+
+impl ObjectFile {
+    /// The object for a program assembled with `-f obj`
+    ///
+    /// Every label is kept, so relocations against local labels can be
+    /// resolved too. Names that are referenced but never defined become
+    /// undefined symbols for the linker to find in another object.
+    pub fn from_program(program: &AssembledProgram) -> Self {
+        let mut labels: Vec<_> = program.label_map.iter().collect();
+        labels.sort_by(|a, b| (a.1 .2, a.0).cmp(&(b.1 .2, b.0)));
+        let mut symbols: Vec<ObjectSymbol> = labels
+            .into_iter()
+            .map(|(name, (_, _, address))| ObjectSymbol {
+                name: name.to_string(),
+                value: *address as u16,
+                defined: true,
+                global: program.globals.contains_key(name),
+            })
+            .collect();
+
+        let mut indices: HashMap<String, u16> = symbols
+            .iter()
+            .enumerate()
+            .map(|(i, sym)| (sym.name.clone(), i as u16))
+            .collect();
+        let mut relocations = Vec::new();
+        for reloc in &program.relocations {
+            let symbol = *indices.entry(reloc.symbol.clone()).or_insert_with(|| {
+                symbols.push(ObjectSymbol {
+                    name: reloc.symbol.clone(),
+                    value: 0,
+                    defined: false,
+                    global: true,
+                });
+                symbols.len() as u16 - 1
+            });
+            relocations.push(ObjectRelocation {
+                offset: reloc.offset as u16,
+                kind: reloc.kind,
+                symbol,
+            });
+        }
+
+        ObjectFile {
+            code: program.binary.clone(),
+            symbols,
+            relocations,
+            metadata: program.metadata_str.clone(),
+        }
+    }
+
+    pub fn write(&self) -> Vec<u8> {
+        let mut bytes = OBJECT_MAGIC.to_vec();
+        for field in [
+            OBJECT_VERSION,
+            self.code.len() as u16,
+            self.symbols.len() as u16,
+            self.relocations.len() as u16,
+        ] {
+            bytes.extend_from_slice(&field.to_be_bytes());
+        }
+        for word in &self.code {
+            bytes.extend_from_slice(&word.to_be_bytes());
+        }
+        for sym in &self.symbols {
+            bytes.push(sym.defined as u8 | (sym.global as u8) << 1);
+            bytes.extend_from_slice(&(sym.name.len() as u16).to_be_bytes());
+            bytes.extend_from_slice(sym.name.as_bytes());
+            bytes.extend_from_slice(&sym.value.to_be_bytes());
+        }
+        for reloc in &self.relocations {
+            bytes.extend_from_slice(&reloc.offset.to_be_bytes());
+            bytes.push(reloc.kind.to_byte());
+            bytes.extend_from_slice(&reloc.symbol.to_be_bytes());
+        }
+        let metadata: Vec<u8> = self.metadata.chars().map(|c| c as u8).collect();
+        bytes.extend_from_slice(&(metadata.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(&metadata);
+        bytes
+    }
+
+    pub fn read(bytes: &[u8]) -> Result<Self, ObjectError> {
+        let mut reader = ObjectReader { bytes, pos: 0 };
+        if reader.take(4)? != OBJECT_MAGIC {
+            return Err(ObjectError::BadMagic);
+        }
+        let version = reader.u16()?;
+        if version != OBJECT_VERSION {
+            return Err(ObjectError::UnsupportedVersion(version));
+        }
+        let (code_len, symbol_count, reloc_count) = (reader.u16()?, reader.u16()?, reader.u16()?);
+
+        let mut object = ObjectFile::default();
+        for _ in 0..code_len {
+            object.code.push(reader.u16()? as i16);
+        }
+        for _ in 0..symbol_count {
+            let flags = reader.u8()?;
+            let len = reader.u16()? as usize;
+            let name = String::from_utf8_lossy(reader.take(len)?).to_string();
+            object.symbols.push(ObjectSymbol {
+                name,
+                value: reader.u16()?,
+                defined: flags & 1 != 0,
+                global: flags & 2 != 0,
+            });
+        }
+        for _ in 0..reloc_count {
+            let offset = reader.u16()?;
+            let kind = reader.u8()?;
+            object.relocations.push(ObjectRelocation {
+                offset,
+                kind: RelocationKind::from_byte(kind)
+                    .ok_or(ObjectError::BadRelocationKind(kind))?,
+                symbol: reader.u16()?,
+            });
+        }
+        let metadata_len = reader.u16()? as usize;
+        object.metadata = reader
+            .take(metadata_len)?
+            .iter()
+            .map(|&b| b as char)
+            .collect();
+        Ok(object)
+    }
+}
+
+struct ObjectReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ObjectReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ObjectError> {
+        let slice = self
+            .bytes
+            .get(self.pos..self.pos + len)
+            .ok_or(ObjectError::Truncated)?;
+        self.pos += len;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, ObjectError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, ObjectError> {
+        let b = self.take(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+}
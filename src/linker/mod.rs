@@ -0,0 +1,4 @@
+pub mod link_core;
+pub mod link_object;
+pub use link_core::*;
+pub use link_object::*;
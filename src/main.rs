@@ -2,19 +2,20 @@ use basm::*;
 use colored::*;
 use std::fs::File;
 use std::io::{self, Write};
-use std::ops::Range;
 use std::sync::{Arc, Mutex};
 
 fn main() {
-    let file = &CONFIG.source.clone().unwrap_or_else(|| "stdin".to_string());
-    let fname = &CONFIG.source.clone().unwrap_or_else(|| "stdin".to_string());
+    let config = declare_config();
+    let options = AssembleOptions::from(&config);
+    let file = &config.source.clone().unwrap_or_else(|| "stdin".to_string());
+    let fname = &config.source.clone().unwrap_or_else(|| "stdin".to_string());
 
-    if CONFIG.repl {
+    if config.repl {
         println!("Welcome to the basm-REPL!");
         let prompt = "repl".green();
         let mut input_string = String::new();
-        let mut fullstr = String::new();
         let mut indicator = ">".green();
+        let mut asm = Assembler::with_options(options.clone());
         let temp_file = Arc::new(Mutex::new(Some(
             TempFile::new().expect("Failed to create temporary file"),
         )));
@@ -74,87 +75,28 @@ fn main() {
                 }
             };
             if input_string.trim() == ":st" {
-                print_symbol_tables();
+                asm.print_symbol_tables();
                 continue;
             }
-            fullstr.push_str(&input_string);
+            if input_string.trim() == ":reset" {
+                asm = Assembler::with_options(options.clone());
+                println!("{}", "session reset.".green());
+                continue;
+            }
+            // diagnostics read their source back from the file, and the spans in
+            // them are relative to this chunk of input only
             let mut file = File::create(&temp_path).expect("Failed to open temporary file");
-            file.write_all(fullstr.as_bytes())
+            file.write_all(input_string.as_bytes())
                 .expect("Failed to write to temporary file");
 
-            let mut error_count = 0;
-
-            let mut parser = match create_parser(fname, &input_string, &mut error_count) {
-                Some(parser) => parser,
-                None => {
-                    indicator = "x".red();
-                    continue;
-                }
-            };
-
-            let mut toks = match parse_tokens(&mut parser, &input_string, &mut error_count) {
-                Some(tokens) => tokens,
-                None => {
-                    indicator = "x".red();
-                    continue;
-                }
-            };
+            let binary = asm.assemble_source(fname, &input_string);
+            asm.apply_error_limit();
 
-            process_includes(&mut toks, &mut error_count);
-            process_macros(&mut toks, &mut error_count);
-            process_start(&mut toks, &mut error_count);
-
-            if error_count > 0 {
-                indicator = "x".red();
-                continue;
+            let failed = asm.diagnostics.iter().any(|d| d.is_error());
+            for diagnostic in asm.diagnostics.drain(..) {
+                print_diagnostic(&diagnostic, config.message_format);
             }
-
-            let toks: Vec<(String, TokenKind, Range<usize>)> = toks
-                .clone()
-                .into_iter()
-                .filter(|(_, x, _)| !matches!(x, TokenKind::Newline))
-                .collect();
-
-            let mut binary = Vec::new();
-            let vecref = &toks;
-            for (ind, (fname, tok, span)) in vecref.iter().enumerate() {
-                match encode((fname, tok, span), fname, &toks.get(ind + 1)) {
-                    Ok(value) => {
-                        binary.extend(value);
-                    }
-                    Err((m, similars)) => {
-                        println!("{m}");
-                        if !similars.is_empty() {
-                            let size = similars.len() - 1;
-                            let max_filename_length = similars
-                                .iter()
-                                .map(|(filename, _)| filename.len())
-                                .max()
-                                .unwrap_or(0);
-                            for (index, (filename, location)) in similars.into_iter().enumerate() {
-                                let (l_num, data) = highlight_range_in_file(&filename, &location);
-                                let connector = if index != size { "├" } else { "╰" };
-                                println!(
-                                    "         {}{} in {:<width$} {}{} {:^6} {} {}",
-                                    connector.bright_red(),
-                                    ">".yellow(),
-                                    filename.green(),
-                                    "-".bright_red(),
-                                    ">".yellow(),
-                                    l_num.to_string().blue(),
-                                    "│".blue(),
-                                    data,
-                                    width = max_filename_length,
-                                );
-                            }
-                            println!();
-                        }
-                        error_count += 1;
-                    }
-                }
-            }
-
-            if error_count > 0 {
+            if failed {
                 indicator = "x".red();
                 continue;
             }
@@ -166,151 +108,99 @@ fn main() {
         }
     }
 
-    let input_string = read_file(&CONFIG.source.clone().unwrap_or_else(|| "stdin".to_string()));
-
-    let mut error_count = 0;
-
-    if CONFIG.verbose {
-        print_msg!("RAW INPUT");
-        println!("{input_string}");
-    }
-
-    let mut parser = match create_parser(file, &input_string, &mut error_count) {
-        Some(parser) => parser,
-        None => {
-            print_errc!(error_count);
-            std::process::exit(1);
-        }
+    // the error limit is applied here instead, so the summary can count the
+    // errors that aren't shown
+    let unlimited = AssembleOptions {
+        error_limit: None,
+        ..options.clone()
     };
-
-    let mut toks = match parse_tokens(&mut parser, &input_string, &mut error_count) {
-        Some(tokens) => tokens,
-        None => {
-            print_errc!(error_count);
-            std::process::exit(1);
-        }
-    };
-
-    process_includes(&mut toks, &mut error_count);
-    process_macros(&mut toks, &mut error_count);
-    process_start(&mut toks, &mut error_count);
-
-    if CONFIG.verbose {
-        print_msg!("COMPLETE TOKENS");
-        for (_, f, _) in &toks {
-            println!("{f}");
-        }
-    }
-
-    let toks: Vec<(String, TokenKind, Range<usize>)> = toks
-        .clone()
-        .into_iter()
-        .filter(|(_, x, _)| !matches!(x, TokenKind::Newline))
-        .collect();
-
-    let mut binary = Vec::new();
-    if toks.is_empty() {
-        println!(
-            "{}: {} appears empty",
-            "warning".yellow().underline(),
-            CONFIG
-                .source
-                .clone()
-                .unwrap_or_else(|| "stdin".to_string())
-                .green()
-        );
-    }
-
-    let vecref = &toks;
-    for (ind, (fname, tok, span)) in vecref.iter().enumerate() {
-        match encode((fname, tok, span), fname, &toks.get(ind + 1)) {
-            Ok(value) => {
-                binary.extend(value);
+    let program = match assemble(file, &unlimited) {
+        Ok(program) => program,
+        Err(mut diagnostics) => {
+            let total = diagnostics.iter().filter(|d| d.is_error()).count();
+            if let Some(limit) = options.error_limit {
+                limit_errors(&mut diagnostics, limit);
+            }
+            for diagnostic in &diagnostics {
+                print_diagnostic(diagnostic, config.message_format);
             }
-            Err((m, similars)) => {
-                println!("{m}");
-                if !similars.is_empty() {
-                    let size = similars.len() - 1;
-                    let max_filename_length = similars
+            if config.message_format == MessageFormat::Human {
+                let count = |severity| {
+                    diagnostics
                         .iter()
-                        .map(|(filename, _)| filename.len())
-                        .max()
-                        .unwrap_or(0);
-                    for (index, (filename, location)) in similars.into_iter().enumerate() {
-                        let (l_num, data) = highlight_range_in_file(&filename, &location);
-                        let connector = if index != size { "├" } else { "╰" };
-                        println!(
-                            "         {}{} in {:<width$} {}{} {:^6} {} {}",
-                            connector.bright_red(),
-                            ">".yellow(),
-                            filename.green(),
-                            "-".bright_red(),
-                            ">".yellow(),
-                            l_num.to_string().blue(),
-                            "│".blue(),
-                            data,
-                            width = max_filename_length,
-                        );
-                    }
-                    println!();
+                        .filter(|d| d.severity == severity)
+                        .count()
+                };
+                print_errors(total, count(Severity::Error));
+                if count(Severity::Warning) > 0 {
+                    print_warnings(count(Severity::Warning));
                 }
-                error_count += 1;
             }
+            std::process::exit(1);
         }
-    }
-
-    print_errc!(error_count);
+    };
 
-    if CONFIG.verbose {
-        print_msg!("SYMBOL TABLES");
-        print_symbol_tables();
+    if !program.warnings.is_empty() {
+        for diagnostic in &program.warnings {
+            print_diagnostic(diagnostic, config.message_format);
+        }
+        if config.message_format == MessageFormat::Human {
+            print_warnings(program.warnings.len());
+        }
     }
 
-    match &CONFIG.output {
+    match &config.output {
         Some(path) => {
-            let mut bytes: Vec<u8> = Vec::new();
-            for value in &binary {
-                bytes.extend_from_slice(&value.to_be_bytes());
+            let bytes = program.render(&options);
+            write_output(path, &bytes, options.verbose);
+            if let Some(listing) = &config.listing {
+                let rendered = program.render_listing();
+                write_output(listing, rendered.as_bytes(), options.verbose);
             }
-
-            let start_bin = *START_LOCATION.lock().unwrap();
-            if !CONFIG.thin {
-                let glob_str = METADATA_STR.lock().unwrap();
-                if glob_str.len() % 2 != 0 {
-                    bytes.insert(0, 0);
-                }
-                for character in glob_str.chars().rev() {
-                    bytes.insert(0, character as u8);
-                }
-                let strlen = if glob_str.len() % 2 == 0 {
-                    glob_str.len()
-                } else {
-                    glob_str.len() + 1
-                };
-                bytes.insert(0, (strlen & 0xff) as u8);
-                bytes.insert(0, ((strlen & 0xff00) >> 8) as u8);
-                bytes.insert(0, (start_bin & 0xff) as u8);
-                bytes.insert(0, ((start_bin & 0xff00) >> 8) as u8);
-                bytes.insert(0, 0x02);
-                bytes.insert(0, 0x01);
+            if let Some(debug_info) = &config.debug_info {
+                let rendered = program.render_debug_info();
+                write_output(debug_info, rendered.as_bytes(), options.verbose);
             }
-            match write_bytes_to_file(path, &bytes) {
-                Ok(()) => (),
-                Err(e) => {
-                    eprintln!("{}: {e}", "error writing to output".bright_red());
-                    error_count += 1;
-                    print_errc!(error_count);
-                }
+            if let Some(symbols) = &config.symbols {
+                let rendered = program.render_symbols(config.symbol_format);
+                write_output(symbols, rendered.as_bytes(), options.verbose);
             }
         }
         _ => {
-            gen_ice!("BINARY NAME APPEARS EMPTY - SHOULD BE SET TO `a.out` BY DEFAULT");
+            eprintln!(
+                "{}",
+                Diagnostic::ice(
+                    format!("{}:{}:{}", file!(), line!(), column!()),
+                    String::from("BINARY NAME APPEARS EMPTY - SHOULD BE SET TO `a.out` BY DEFAULT"),
+                )
+            );
+            std::process::exit(1);
         }
     }
 }
 
-pub fn write_bytes_to_file(filename: &str, encoded_instructions: &[u8]) -> io::Result<()> {
-    if CONFIG.verbose {
+// writes an output file, exiting if that fails
+fn write_output(path: &str, bytes: &[u8], verbose: bool) {
+    if let Err(e) = write_bytes_to_file(path, bytes, verbose) {
+        eprintln!("{}: {e}", "error writing to output".bright_red());
+        print_errors(1, 1);
+        std::process::exit(1);
+    }
+}
+
+fn print_diagnostic(diagnostic: &Diagnostic, format: MessageFormat) {
+    match format {
+        MessageFormat::Human => println!("{diagnostic}\n"),
+        MessageFormat::Json => println!("{}", diagnostic.to_json()),
+    }
+}
+
+pub fn write_bytes_to_file(
+    filename: &str,
+    encoded_instructions: &[u8],
+    verbose: bool,
+) -> io::Result<()> {
+    if verbose {
         println!("{}", "wrote to file.".green());
     }
     let mut file = File::create(filename)?;
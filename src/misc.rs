@@ -1,28 +1,11 @@
-#[macro_export]
-macro_rules! print_errc {
-    ( $error_count:expr ) => {
-        if $error_count > 0 {
-            print_errors($error_count);
-            std::process::exit(1);
-        }
-    };
-}
-
 #[macro_export]
 macro_rules! gen_ice { // Generate (I)nternal (C)ompiler (E)rror
     ($($arg:tt)*) => {
-        {
-            eprintln!(
-                "!!! {} !!! [{}] IN {}:{}:{}\nMESSAGE: {} ",
-                "FATAL".red().bold(),
-                "INTERNAL COMPILER ERROR".red().bold(),
-                file!(),
-                line!(),
-                column!(),
-                format!($($arg)*),
-            );
-            std::process::exit(1);
-        }
+        return Err($crate::Diagnostic::ice(
+            format!("{}:{}:{}", file!(), line!(), column!()),
+            format!($($arg)*),
+        )
+        .into())
     };
 }
 
@@ -107,37 +90,48 @@ pub fn levenshtein(a: &str, b: &str) -> usize {
 }
 
 use colored::*;
-pub fn print_errors(error_count: i32) {
+// `shown` is how many of the errors were printed, when some were left out
+pub fn print_errors(error_count: usize, shown: usize) {
     let msg = if error_count == 1 {
         "error generated"
     } else {
         "errors generated"
     };
+    let shown = if shown < error_count {
+        format!(", {shown} shown")
+    } else {
+        String::new()
+    };
     println!(
-        "compilation unsuccessful\n{} {}.",
+        "compilation unsuccessful\n{} {}{shown}.",
         error_count.to_string().bright_red(),
         msg,
     );
 }
 
+pub fn print_warnings(warning_count: usize) {
+    let msg = if warning_count == 1 {
+        "warning generated"
+    } else {
+        "warnings generated"
+    };
+    println!("{} {}.", warning_count.to_string().yellow(), msg);
+}
+
 use crate::*;
 pub fn handle_core_error(
     fname: &str,
     loc: &std::ops::Range<usize>,
-    error_count: &mut i32,
+    errors: &mut Vec<Diagnostic>,
+    code: DiagnosticCode,
     message: &str,
     help: Option<String>,
 ) {
-    let problem = ParserError {
-        file: fname.to_string(),
-        help,
-        input: read_file(fname),
-        message: message.to_string(),
-        start_pos: loc.start,
-        last_pos: loc.end,
-    };
-    *error_count += 1;
-    println!("{problem}");
+    errors.push(
+        Diagnostic::error(code, message)
+            .at(fname, loc.clone())
+            .with_help_opt(help),
+    );
 }
 
 use std::ops::Range;
@@ -166,7 +160,7 @@ pub fn highlight_range_in_file(file_path: &str, range: &Range<usize>) -> (usize,
         current_index += line_length + 1;
         line_number += 1;
     }
-    panic!("Failed to highlight_range_in_file");
+    (line_number, String::new())
 }
 
 use std::fs::{self, File};
@@ -0,0 +1,13 @@
+pub mod out_array;
+pub mod out_bin;
+pub mod out_core;
+pub mod out_debug;
+pub mod out_header;
+pub mod out_ihex;
+pub mod out_listing;
+pub mod out_mem;
+pub mod out_srec;
+pub mod out_symbols;
+pub use out_array::*;
+pub use out_core::*;
+pub use out_header::*;
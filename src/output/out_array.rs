@@ -0,0 +1,121 @@
+use crate::*;
+use colored::*;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// words per line of the array
+const ARRAY_ROW_LEN: usize = 8;
+
+impl AssembledProgram {
+    /// A C header with the program as a `uint16_t` array, its start address
+    /// and the address of every label
+    pub fn render_c_array(&self, name: &str) -> String {
+        let upper = name.to_uppercase();
+        let mut out = String::from("/* Generated by basm, do not edit */\n");
+        let _ = writeln!(out, "#ifndef {upper}_H\n#define {upper}_H\n");
+        out.push_str("#include <stdint.h>\n\n");
+        let _ = writeln!(out, "#define {upper}_LEN {}", self.binary.len());
+        let _ = writeln!(
+            out,
+            "#define {upper}_START 0x{:04x}",
+            self.start_location & 0xffff
+        );
+        for (label, address) in self.sorted_labels() {
+            let _ = writeln!(
+                out,
+                "#define {upper}_LABEL_{} 0x{address:04x}",
+                label.to_uppercase()
+            );
+        }
+        let _ = writeln!(out, "\nstatic const uint16_t {name}[{upper}_LEN] = {{");
+        out.push_str(&self.array_rows());
+        let _ = writeln!(out, "}};\n\n#endif /* {upper}_H */");
+        out
+    }
+
+    /// A Rust module body with the program as a `[u16; N]` constant, its start
+    /// address and the address of every label, suitable for `include!`
+    pub fn render_rust_array(&self, name: &str) -> String {
+        let upper = name.to_uppercase();
+        let mut out = String::from("// Generated by basm, do not edit\n\n");
+        let _ = writeln!(
+            out,
+            "pub const {upper}_START: u16 = 0x{:04x};",
+            self.start_location & 0xffff
+        );
+        for (label, address) in self.sorted_labels() {
+            let _ = writeln!(
+                out,
+                "pub const {upper}_LABEL_{}: u16 = 0x{address:04x};",
+                label.to_uppercase()
+            );
+        }
+        let _ = writeln!(out, "\npub const {upper}: [u16; {}] = [", self.binary.len());
+        out.push_str(&self.array_rows());
+        out.push_str("];\n");
+        out
+    }
+
+    // labels in address order, then by name for labels sharing an address
+    fn sorted_labels(&self) -> Vec<(&String, usize)> {
+        let mut labels: Vec<(&String, usize)> = self
+            .label_map
+            .iter()
+            .map(|(label, (_, _, address))| (label, *address & 0xffff))
+            .collect();
+        labels.sort_by(|a, b| (a.1, a.0).cmp(&(b.1, b.0)));
+        labels
+    }
+
+    fn array_rows(&self) -> String {
+        let mut out = String::new();
+        for row in self.binary.chunks(ARRAY_ROW_LEN) {
+            let words: Vec<String> = row
+                .iter()
+                .map(|w| format!("0x{:04x},", *w as u16))
+                .collect();
+            let _ = writeln!(out, "    {}", words.join(" "));
+        }
+        out
+    }
+}
+
+/// Errors for labels whose names are the same once upper-cased, since the
+/// array outputs would then declare the same name twice
+pub fn array_name_clashes(labels: &LabelMap, name: &str) -> Vec<Diagnostic> {
+    let mut by_name: HashMap<String, Vec<_>> = HashMap::new();
+    for (label, declared) in labels.iter() {
+        by_name
+            .entry(label.to_uppercase())
+            .or_default()
+            .push((label, declared));
+    }
+    let mut clashes: Vec<_> = by_name.into_iter().filter(|(_, v)| v.len() > 1).collect();
+    clashes.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut errors = Vec::new();
+    for (upper, mut clashing) in clashes {
+        clashing.sort_by_key(|(label, (file, place, _))| (file.to_string(), place.start, *label));
+        let (first, (first_file, first_place, _)) = clashing[0];
+        for (label, (file, place, _)) in &clashing[1..] {
+            errors.push(
+                Diagnostic::error(
+                    DiagnosticCode::DuplicateLabel,
+                    format!(
+                        "labels `{}` and `{}` would both be named `{}_LABEL_{upper}`",
+                        first.magenta(),
+                        label.magenta(),
+                        name.to_uppercase()
+                    ),
+                )
+                .at(file, place.clone())
+                .with_label(
+                    first_file,
+                    first_place.clone(),
+                    Some(format!("`{first}` declared here")),
+                )
+                .with_help("array outputs upper-case label names, so rename one of them"),
+            );
+        }
+    }
+    errors
+}
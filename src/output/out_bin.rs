@@ -0,0 +1,15 @@
+use crate::*;
+
+impl AssembledProgram {
+    /// Raw big-endian words, preceded by a header in the `header` layout
+    ///
+    /// See [`BinaryHeader`] for what each layout holds.
+    pub fn render_binary(&self, header: HeaderFormat) -> Vec<u8> {
+        let code = self.words_as_bytes();
+        match header {
+            HeaderFormat::None => code,
+            HeaderFormat::V1 => BinaryHeader::for_program(self, 1).write(&code),
+            HeaderFormat::V2 => BinaryHeader::for_program(self, 2).write(&code),
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use crate::*;
+
+impl AssembledProgram {
+    /// Renders the program as the bytes of an output file in `options.output_format`
+    pub fn render(&self, options: &AssembleOptions) -> Vec<u8> {
+        match options.output_format {
+            OutputFormat::Binary => self.render_binary(options.header),
+            OutputFormat::Ihex => self.render_ihex().into_bytes(),
+            OutputFormat::Srec => self.render_srec().into_bytes(),
+            OutputFormat::Object => ObjectFile::from_program(self).write(),
+            OutputFormat::Memh => self.render_readmem(false).into_bytes(),
+            OutputFormat::Memb => self.render_readmem(true).into_bytes(),
+            OutputFormat::Logisim => self.render_logisim().into_bytes(),
+            OutputFormat::CArray => self.render_c_array(&options.array_name).into_bytes(),
+            OutputFormat::RustArray => self.render_rust_array(&options.array_name).into_bytes(),
+        }
+    }
+
+    /// The program as big-endian bytes, without any header
+    pub fn words_as_bytes(&self) -> Vec<u8> {
+        self.binary
+            .iter()
+            .flat_map(|word| word.to_be_bytes())
+            .collect()
+    }
+
+    /// Byte address of the first word
+    ///
+    /// BELLE addresses words, while the hex formats address bytes, so every
+    /// word address is doubled.
+    pub fn byte_address(&self) -> u32 {
+        (self.start_location as u32 & 0xffff) * 2
+    }
+
+    /// Byte address of the entry point
+    pub fn entry_byte_address(&self) -> u32 {
+        (self.entry_point as u32 & 0xffff) * 2
+    }
+}
+
+/// 1-based line of the byte at `offset` in `file`
+pub fn line_number(file: &str, offset: usize) -> usize {
+    let text = read_file(file);
+    let before = &text.as_bytes()[..offset.min(text.len())];
+    before.iter().filter(|&&byte| byte == b'\n').count() + 1
+}
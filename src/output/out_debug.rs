@@ -0,0 +1,63 @@
+use crate::*;
+use std::collections::HashMap;
+use std::ops::Range;
+
+impl AssembledProgram {
+    /// The debug info written by `-g`, mapping every word to its source
+    ///
+    /// A single JSON object, with one entry in `words` per word of the
+    /// program. `expansion` lists the macro calls a word was expanded from,
+    /// outermost first, and is empty for words written directly in a file.
+    pub fn render_debug_info(&self) -> String {
+        let mut sources = HashMap::new();
+        let start = self.start_location as usize;
+        let words: Vec<String> = self
+            .origins
+            .iter()
+            .enumerate()
+            .map(|(index, origin)| {
+                let expansion: Vec<String> = origin
+                    .expansion
+                    .iter()
+                    .map(|call| {
+                        format!(
+                            "{{\"macro\":{},{}}}",
+                            json_string(&call.name),
+                            debug_position(&mut sources, &call.file, &call.span)
+                        )
+                    })
+                    .collect();
+                format!(
+                    "{{\"address\":{},{},\"expansion\":[{}]}}",
+                    (start + index) & 0xffff,
+                    debug_position(&mut sources, &origin.file, &origin.span),
+                    expansion.join(",")
+                )
+            })
+            .collect();
+        format!(
+            "{{\"version\":1,\"start\":{},\"words\":[{}]}}\n",
+            start & 0xffff,
+            words.join(",")
+        )
+    }
+}
+
+// `"file":..,"line":..,"column":..` for the start of `span`, reading each file once
+fn debug_position(
+    sources: &mut HashMap<String, String>,
+    file: &str,
+    span: &Range<usize>,
+) -> String {
+    let text = sources
+        .entry(file.to_string())
+        .or_insert_with(|| read_file(file));
+    let (line, column) = match line_column(text, span.start) {
+        Some((line, column)) => (line.to_string(), column.to_string()),
+        None => (String::from("null"), String::from("null")),
+    };
+    format!(
+        "\"file\":{},\"line\":{line},\"column\":{column}",
+        json_string(file)
+    )
+}
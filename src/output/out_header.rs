@@ -0,0 +1,220 @@
+use crate::*;
+use std::fmt;
+
+/// Magic bytes of a version 1 header
+pub const HEADER_V1_MAGIC: [u8; 2] = [0x01, 0x02];
+/// Magic bytes of a version 2 (or later) header
+pub const HEADER_MAGIC: [u8; 4] = *b"BELL";
+/// Size of the fixed part of a version 2 header, before the metadata
+pub const HEADER_V2_LEN: usize = 20;
+
+/// The header in front of the code in `bin` output
+///
+/// Version 1 is the original layout, all big-endian:
+///
+/// | offset | size | field |
+/// | :----- | :--- | :---- |
+/// | 0 | 2 | magic, `0x01 0x02` |
+/// | 2 | 2 | start address |
+/// | 4 | 2 | metadata length, padded to an even number of bytes |
+/// | 6 | n | metadata, padded with a zero byte |
+///
+/// Version 2 adds a version, the code length and a checksum:
+///
+/// | offset | size | field |
+/// | :----- | :--- | :---- |
+/// | 0 | 4 | magic, `BELL` |
+/// | 4 | 2 | format version, `2` |
+/// | 6 | 2 | flags, reserved and always `0` |
+/// | 8 | 2 | entry point (word address) |
+/// | 10 | 2 | load address of the first word (word address) |
+/// | 12 | 2 | code length in words |
+/// | 14 | 2 | metadata length in bytes, without padding |
+/// | 16 | 4 | CRC-32 of every other byte of the file |
+/// | 20 | n | metadata, padded with a zero byte to an even length |
+///
+/// The code follows the header in both versions, as big-endian words.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryHeader {
+    pub version: u16,
+    pub entry_point: u16,
+    pub load_address: u16,
+    pub code_length: u16,
+    pub metadata: String,
+}
+
+/// Why a binary's header couldn't be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderError {
+    /// The file ends before the header does
+    Truncated,
+    /// Neither magic number is at the start of the file
+    BadMagic,
+    UnsupportedVersion(u16),
+    /// The code section isn't as long as the header says
+    LengthMismatch {
+        expected: usize,
+        found: usize,
+    },
+    ChecksumMismatch {
+        expected: u32,
+        found: u32,
+    },
+}
+
+impl fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeaderError::Truncated => write!(f, "the header is cut short"),
+            HeaderError::BadMagic => write!(f, "the file does not start with a BELLE header"),
+            HeaderError::UnsupportedVersion(v) => write!(f, "unsupported header version {v}"),
+            HeaderError::LengthMismatch { expected, found } => write!(
+                f,
+                "the header says there are {expected} bytes of code, but there are {found}"
+            ),
+            HeaderError::ChecksumMismatch { expected, found } => write!(
+                f,
+                "checksum mismatch: the header says {expected:08x}, the file is {found:08x}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HeaderError {}
+
+impl BinaryHeader {
+    /// The header of `program` in the given layout version
+    pub fn for_program(program: &AssembledProgram, version: u16) -> Self {
+        BinaryHeader {
+            version,
+            entry_point: program.entry_point as u16,
+            load_address: program.start_location as u16,
+            code_length: program.binary.len() as u16,
+            metadata: program.metadata_str.clone(),
+        }
+    }
+
+    /// The header followed by `code`, as the bytes of a complete binary
+    pub fn write(&self, code: &[u8]) -> Vec<u8> {
+        let metadata: Vec<u8> = self.metadata.chars().map(|c| c as u8).collect();
+        let padding = metadata.len() % 2;
+        let mut bytes = Vec::new();
+        if self.version == 1 {
+            bytes.extend_from_slice(&HEADER_V1_MAGIC);
+            bytes.extend_from_slice(&self.entry_point.to_be_bytes());
+            bytes.extend_from_slice(&((metadata.len() + padding) as u16).to_be_bytes());
+            bytes.extend_from_slice(&metadata);
+            bytes.extend(std::iter::repeat_n(0, padding));
+            bytes.extend_from_slice(code);
+            return bytes;
+        }
+        bytes.extend_from_slice(&HEADER_MAGIC);
+        for field in [
+            self.version,
+            0,
+            self.entry_point,
+            self.load_address,
+            self.code_length,
+            metadata.len() as u16,
+        ] {
+            bytes.extend_from_slice(&field.to_be_bytes());
+        }
+        // the checksum goes in once everything it covers is in place
+        bytes.extend_from_slice(&[0; 4]);
+        bytes.extend_from_slice(&metadata);
+        bytes.extend(std::iter::repeat_n(0, padding));
+        bytes.extend_from_slice(code);
+        let crc = header_crc(&bytes);
+        bytes[16..20].copy_from_slice(&crc.to_be_bytes());
+        bytes
+    }
+
+    /// Reads and validates the header of a binary, returning it with the code
+    /// that follows
+    pub fn read(bytes: &[u8]) -> Result<(Self, &[u8]), HeaderError> {
+        let field = |offset: usize| -> Result<u16, HeaderError> {
+            match bytes.get(offset..offset + 2) {
+                Some(b) => Ok(u16::from_be_bytes([b[0], b[1]])),
+                None => Err(HeaderError::Truncated),
+            }
+        };
+
+        if bytes.starts_with(&HEADER_MAGIC) {
+            let version = field(4)?;
+            if version != 2 {
+                return Err(HeaderError::UnsupportedVersion(version));
+            }
+            let metadata_len = field(14)? as usize;
+            let code_start = HEADER_V2_LEN + metadata_len + metadata_len % 2;
+            let (Some(stored), Some(metadata)) = (
+                bytes.get(16..20),
+                bytes.get(HEADER_V2_LEN..HEADER_V2_LEN + metadata_len),
+            ) else {
+                return Err(HeaderError::Truncated);
+            };
+            let code = bytes.get(code_start..).ok_or(HeaderError::Truncated)?;
+            let header = BinaryHeader {
+                version,
+                entry_point: field(8)?,
+                load_address: field(10)?,
+                code_length: field(12)?,
+                metadata: metadata.iter().map(|&b| b as char).collect(),
+            };
+            let expected = u32::from_be_bytes([stored[0], stored[1], stored[2], stored[3]]);
+            let found = header_crc(bytes);
+            if expected != found {
+                return Err(HeaderError::ChecksumMismatch { expected, found });
+            }
+            if code.len() != header.code_length as usize * 2 {
+                return Err(HeaderError::LengthMismatch {
+                    expected: header.code_length as usize * 2,
+                    found: code.len(),
+                });
+            }
+            return Ok((header, code));
+        }
+
+        if bytes.starts_with(&HEADER_V1_MAGIC) {
+            let start = field(2)?;
+            let metadata_len = field(4)? as usize;
+            let metadata = bytes
+                .get(6..6 + metadata_len)
+                .ok_or(HeaderError::Truncated)?;
+            let code = &bytes[6 + metadata_len..];
+            let header = BinaryHeader {
+                version: 1,
+                entry_point: start,
+                load_address: start,
+                code_length: (code.len() / 2) as u16,
+                // the padding can't be told apart from the string, so drop it
+                metadata: metadata
+                    .iter()
+                    .map(|&b| b as char)
+                    .collect::<String>()
+                    .trim_end_matches('\0')
+                    .to_string(),
+            };
+            return Ok((header, code));
+        }
+
+        Err(HeaderError::BadMagic)
+    }
+}
+
+// CRC-32 of a version 2 binary, skipping the bytes the checksum is stored in
+fn header_crc(bytes: &[u8]) -> u32 {
+    let covered = bytes[..16]
+        .iter()
+        .chain(bytes.get(20..).unwrap_or_default());
+    !covered.fold(!0, |crc, &byte| crc32_byte(crc, byte))
+}
+
+// one step of the reflected CRC-32 used by zlib and PNG (polynomial 0x04C11DB7)
+fn crc32_byte(crc: u32, byte: u8) -> u32 {
+    let mut crc = crc ^ byte as u32;
+    for _ in 0..8 {
+        let mask = (crc & 1).wrapping_neg();
+        crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+    }
+    crc
+}
@@ -0,0 +1,60 @@
+use crate::*;
+use std::fmt::Write;
+
+const IHEX_DATA: u8 = 0x00;
+const IHEX_EOF: u8 = 0x01;
+const IHEX_EXTENDED_LINEAR: u8 = 0x04;
+const IHEX_START_LINEAR: u8 = 0x05;
+
+// bytes of data per record
+const IHEX_RECORD_LEN: usize = 16;
+
+impl AssembledProgram {
+    /// Intel HEX, with an extended linear address record whenever the upper
+    /// 16 bits of the address change and a start linear address record for
+    /// the entry point
+    pub fn render_ihex(&self) -> String {
+        let mut out = String::new();
+        let mut upper = 0;
+        let mut address = self.byte_address();
+        for chunk in self.words_as_bytes().chunks(IHEX_RECORD_LEN) {
+            // a record can't cross a 64K boundary, but words never straddle one
+            // and the record length is even, so it's enough to check the start
+            if address >> 16 != upper {
+                upper = address >> 16;
+                ihex_record(
+                    &mut out,
+                    IHEX_EXTENDED_LINEAR,
+                    0,
+                    &(upper as u16).to_be_bytes(),
+                );
+            }
+            ihex_record(&mut out, IHEX_DATA, address as u16, chunk);
+            address += chunk.len() as u32;
+        }
+        ihex_record(
+            &mut out,
+            IHEX_START_LINEAR,
+            0,
+            &self.entry_byte_address().to_be_bytes(),
+        );
+        ihex_record(&mut out, IHEX_EOF, 0, &[]);
+        out
+    }
+}
+
+// `:LLAAAATT<data>CC`, where CC is the two's complement of the sum of every other byte
+fn ihex_record(out: &mut String, kind: u8, address: u16, data: &[u8]) {
+    let mut record = vec![data.len() as u8];
+    record.extend_from_slice(&address.to_be_bytes());
+    record.push(kind);
+    record.extend_from_slice(data);
+    let sum = record.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+    record.push(sum.wrapping_neg());
+
+    out.push(':');
+    for byte in record {
+        let _ = write!(out, "{byte:02X}");
+    }
+    out.push('\n');
+}
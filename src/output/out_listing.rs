@@ -0,0 +1,209 @@
+use crate::*;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::ops::Range;
+
+// width of the address, hex and binary columns, and the gaps between them
+const CODE_WIDTH: usize = 4 + 2 + 4 + 2 + 16;
+
+impl AssembledProgram {
+    /// A listing of every source line next to the words it was assembled into
+    ///
+    /// Lines of macro bodies are shown after the line that called the macro,
+    /// marked with a `+`, and a line assembled more than once gets a row for
+    /// each time. The lines of each file are shown in order, so lines
+    /// that produced no words (comments, constants, labels) still appear in
+    /// context. Labels and constants are listed at the end.
+    pub fn render_listing(&self) -> String {
+        let mut listing = Listing::default();
+        listing.visit(&self.source);
+
+        let start = self.start_location as usize;
+        let mut last_row = None;
+        for (index, (word, origin)) in self.binary.iter().zip(&self.origins).enumerate() {
+            let line = listing.line_of(&origin.file, origin.span.start);
+            let expanded = !origin.expansion.is_empty();
+            let source = if last_row == Some(origin.token) {
+                // the rest of the words of a line that produced several
+                None
+            } else {
+                listing.show_includes(&self.includes, &origin.file);
+                match origin.expansion.first() {
+                    Some(call) => {
+                        let call_line = listing.line_of(&call.file, call.span.start);
+                        listing.show_until(&call.file, call_line + 1);
+                        // blocks repeated inside it have their headers shown the same way
+                        for block in &origin.expansion[1..] {
+                            if block.name.starts_with('.') && block.file == origin.file {
+                                let block_line = listing.line_of(&block.file, block.span.start);
+                                listing.show_until(&block.file, block_line + 1);
+                            }
+                        }
+                    }
+                    None => {
+                        listing.show_until(&origin.file, line);
+                        listing.switch_to(&origin.file);
+                    }
+                }
+                Some((line, listing.text(&origin.file, line)))
+            };
+            let marker = if expanded { '+' } else { ' ' };
+            let _ = write!(
+                listing.out,
+                "{:04x}  {:04x}  {:016b}",
+                (start + index) & 0xffff,
+                *word as u16,
+                *word as u16
+            );
+            match source {
+                Some((line, text)) => {
+                    let _ = writeln!(listing.out, "  {marker}{:>5}  {text}", line + 1);
+                }
+                None => listing.out.push('\n'),
+            }
+            // a repeated body comes right after its .rept or .irp, so the copies
+            // stand in for its lines, unlike a macro body shown where it's declared
+            let repeated = match origin.expansion.first() {
+                Some(site) => site.name.starts_with('.') && site.file == origin.file,
+                None => false,
+            };
+            if !expanded || repeated {
+                listing.mark_shown(&origin.file, line + 1);
+            }
+            last_row = Some(origin.token);
+        }
+
+        for file in listing.order.clone() {
+            let end = listing.lines(&file).len();
+            listing.show_until(&file, end);
+        }
+
+        let mut out = listing.out;
+        out.push_str(&self.listing_symbols());
+        out
+    }
+
+    fn listing_symbols(&self) -> String {
+        let mut out = String::new();
+        let mut labels: Vec<_> = self.label_map.iter().collect();
+        labels.sort_by(|a, b| (a.1 .2, a.0).cmp(&(b.1 .2, b.0)));
+        let mut constants: Vec<_> = self.v_map.iter().collect();
+        constants.sort_by(|a, b| a.0.cmp(b.0));
+
+        let width = labels
+            .iter()
+            .map(|(name, _)| name.len())
+            .chain(constants.iter().map(|(name, _)| name.len()))
+            .max()
+            .unwrap_or(0);
+
+        out.push_str("\n; labels\n");
+        for (name, (file, place, address)) in labels {
+            let _ = writeln!(
+                out,
+                "{:04x}  {name:<width$}  {file}:{}",
+                address & 0xffff,
+                line_number(file, place.start)
+            );
+        }
+        out.push_str("\n; constants\n");
+        for (name, (file, place, value)) in constants {
+            let declared = if file == COMMAND_LINE {
+                String::from("command line")
+            } else {
+                format!("{file}:{}", line_number(file, place.start))
+            };
+            let _ = writeln!(
+                out,
+                "{:04x}  {name:<width$}  {declared}  ({value})",
+                *value as u16
+            );
+        }
+        out
+    }
+}
+
+// the source files of a listing and how far each has been shown
+#[derive(Default)]
+struct Listing {
+    out: String,
+    files: HashMap<String, (Vec<usize>, String)>,
+    shown: HashMap<String, usize>,
+    // files in the order they were first seen
+    order: Vec<String>,
+    current: Option<String>,
+}
+
+impl Listing {
+    fn visit(&mut self, file: &str) {
+        if !self.files.contains_key(file) {
+            let text = read_file(file);
+            let starts = std::iter::once(0)
+                .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+                .filter(|&start| start < text.len())
+                .collect();
+            self.files.insert(file.to_string(), (starts, text));
+            self.order.push(file.to_string());
+        }
+    }
+
+    fn lines(&mut self, file: &str) -> &[usize] {
+        self.visit(file);
+        &self.files[file].0
+    }
+
+    // 0-based line holding the byte at `offset`
+    fn line_of(&mut self, file: &str, offset: usize) -> usize {
+        let starts = self.lines(file);
+        starts
+            .partition_point(|&start| start <= offset)
+            .saturating_sub(1)
+    }
+
+    fn text(&mut self, file: &str, line: usize) -> String {
+        self.visit(file);
+        let (starts, text) = &self.files[file];
+        let Some(&start) = starts.get(line) else {
+            return String::new();
+        };
+        let end = starts.get(line + 1).copied().unwrap_or(text.len());
+        text[start..end].trim_end().to_string()
+    }
+
+    // starts a new section when the lines that follow come from another file
+    fn switch_to(&mut self, file: &str) {
+        if self.current.as_deref() != Some(file) {
+            self.current = Some(file.to_string());
+            let _ = writeln!(self.out, "; {file}");
+        }
+    }
+
+    fn mark_shown(&mut self, file: &str, end: usize) {
+        let shown = self.shown.entry(file.to_string()).or_insert(0);
+        *shown = (*shown).max(end);
+    }
+
+    // shows the lines up to the @include that read `file`, and the ones up to
+    // the @include that read that file, and so on, before any line of `file`
+    fn show_includes(&mut self, includes: &SymbolTable<(String, Range<usize>)>, file: &str) {
+        if let Some((from, place)) = includes.get(file) {
+            self.show_includes(includes, from);
+            let line = self.line_of(from, place.start);
+            self.show_until(from, line + 1);
+        }
+    }
+
+    // shows every line of `file` before `end` that hasn't been shown yet
+    fn show_until(&mut self, file: &str, end: usize) {
+        let shown = self.shown.get(file).copied().unwrap_or(0);
+        if end <= shown {
+            return;
+        }
+        self.switch_to(file);
+        for line in shown..end {
+            let text = self.text(file, line);
+            let _ = writeln!(self.out, "{:CODE_WIDTH$}   {:>5}  {text}", "", line + 1);
+        }
+        self.mark_shown(file, end);
+    }
+}
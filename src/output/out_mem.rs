@@ -0,0 +1,52 @@
+use crate::*;
+use std::fmt::Write;
+
+impl AssembledProgram {
+    /// One word per line for Verilog's `$readmemh` (`binary == false`) or
+    /// `$readmemb`, starting with an `@address` jump to the first word
+    ///
+    /// The words of `gaps` are left out, with another jump to the word after
+    /// each. Addresses are word addresses, as memories in Verilog are indexed
+    /// by word.
+    pub fn render_readmem(&self, binary: bool) -> String {
+        let mut empty = vec![false; self.binary.len()];
+        for gap in &self.gaps {
+            empty[gap.clone()].fill(true);
+        }
+        let mut out = String::new();
+        let mut jump = true;
+        for (index, word) in self.binary.iter().enumerate() {
+            if empty[index] {
+                jump = true;
+                continue;
+            }
+            if jump {
+                let address = self.start_location as usize + index;
+                let _ = writeln!(out, "@{:04x}", address & 0xffff);
+                jump = false;
+            }
+            let _ = if binary {
+                writeln!(out, "{:016b}", *word as u16)
+            } else {
+                writeln!(out, "{:04x}", *word as u16)
+            };
+        }
+        out
+    }
+
+    /// A Logisim `v2.0 raw` memory image, one word per line
+    ///
+    /// Logisim images have no way to jump to an address, so the words before
+    /// the start address are written as a run of zeros.
+    pub fn render_logisim(&self) -> String {
+        let mut out = String::from("v2.0 raw\n");
+        let start = self.start_location & 0xffff;
+        if start > 0 {
+            let _ = writeln!(out, "{start}*0");
+        }
+        for word in &self.binary {
+            let _ = writeln!(out, "{:04x}", *word as u16);
+        }
+        out
+    }
+}
@@ -0,0 +1,63 @@
+use crate::*;
+use std::fmt::Write;
+
+// bytes of data per record
+const SREC_RECORD_LEN: usize = 16;
+
+impl AssembledProgram {
+    /// Motorola S-records, with the metadata string in the S0 header
+    ///
+    /// Programs that fit below byte address 0x10000 use S1 data and an S9
+    /// start record, anything larger uses S2 and S8 with 24-bit addresses.
+    pub fn render_srec(&self) -> String {
+        let bytes = self.words_as_bytes();
+        let end = self.byte_address() as usize + bytes.len();
+        let (data_kind, start_kind, address_len) =
+            if end <= 0x10000 { (1, 9, 2) } else { (2, 8, 3) };
+
+        let mut out = String::new();
+        let header: Vec<u8> = self
+            .metadata_str
+            .chars()
+            .map(|c| c as u8)
+            .take(250)
+            .collect();
+        srec_record(&mut out, 0, 0, 2, &header);
+
+        let mut address = self.byte_address();
+        let mut count = 0;
+        for chunk in bytes.chunks(SREC_RECORD_LEN) {
+            srec_record(&mut out, data_kind, address, address_len, chunk);
+            address += chunk.len() as u32;
+            count += 1;
+        }
+        // S5 only has room for a 16-bit count, and it's optional anyway
+        if count <= 0xffff {
+            srec_record(&mut out, 5, count, 2, &[]);
+        }
+        srec_record(
+            &mut out,
+            start_kind,
+            self.entry_byte_address(),
+            address_len,
+            &[],
+        );
+        out
+    }
+}
+
+// `S<kind><count><address><data><checksum>`, where the checksum is the one's
+// complement of the sum of the count, address and data bytes
+fn srec_record(out: &mut String, kind: u8, address: u32, address_len: usize, data: &[u8]) {
+    let mut record = vec![(address_len + data.len() + 1) as u8];
+    record.extend_from_slice(&address.to_be_bytes()[4 - address_len..]);
+    record.extend_from_slice(data);
+    let sum = record.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+    record.push(!sum);
+
+    let _ = write!(out, "S{kind}");
+    for byte in record {
+        let _ = write!(out, "{byte:02X}");
+    }
+    out.push('\n');
+}
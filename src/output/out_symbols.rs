@@ -0,0 +1,147 @@
+use crate::*;
+use std::fmt::Write;
+use std::ops::Range;
+
+impl AssembledProgram {
+    /// The symbol file written by `--symbols`, in `format`
+    pub fn render_symbols(&self, format: SymbolFormat) -> String {
+        match format {
+            SymbolFormat::Text => self.render_symbols_text(),
+            SymbolFormat::Json => self.render_symbols_json(),
+        }
+    }
+
+    /// One symbol per line, with tab-separated fields:
+    ///
+    /// ```text
+    /// label   <name>  <address, 4 hex digits>  <file>  <line>
+    /// const   <name>  <value, decimal>         <file>  <line>
+    /// macro   <name>  <params or ->            <file>  <line>
+    /// ```
+    ///
+    /// Macro parameters are written as `name:type`, separated by commas.
+    /// Constants defined on the command line have `-` for their file and
+    /// line. Lines starting with `;` are comments.
+    pub fn render_symbols_text(&self) -> String {
+        let mut out = String::from("; basm symbols v1\n");
+        for (name, file, place, address) in self.sorted_symbol_labels() {
+            let line = line_number(file, place.start);
+            let _ = writeln!(out, "label\t{name}\t{address:04x}\t{file}\t{line}");
+        }
+        for (name, file, place, value) in self.sorted_symbol_constants() {
+            if file == COMMAND_LINE {
+                let _ = writeln!(out, "const\t{name}\t{value}\t-\t-");
+                continue;
+            }
+            let line = line_number(file, place.start);
+            let _ = writeln!(out, "const\t{name}\t{value}\t{file}\t{line}");
+        }
+        for mac in self.sorted_symbol_macros() {
+            let params: Vec<String> = mac
+                .parameters
+                .iter()
+                .map(|(_, arg, _)| format!("{}:{}", arg.name, arg.arg_type.keyword()))
+                .collect();
+            let params = if params.is_empty() {
+                String::from("-")
+            } else {
+                params.join(",")
+            };
+            let line = line_number(&mac.file, mac.name.1.start);
+            let _ = writeln!(out, "macro\t{}\t{params}\t{}\t{line}", mac.name.0, mac.file);
+        }
+        out
+    }
+
+    /// A single JSON object with `labels`, `constants` and `macros` arrays
+    pub fn render_symbols_json(&self) -> String {
+        let labels: Vec<String> = self
+            .sorted_symbol_labels()
+            .into_iter()
+            .map(|(name, file, place, address)| {
+                format!(
+                    "{{\"name\":{},\"address\":{address},\"file\":{},\"line\":{}}}",
+                    json_string(name),
+                    json_string(file),
+                    line_number(file, place.start)
+                )
+            })
+            .collect();
+        let constants: Vec<String> = self
+            .sorted_symbol_constants()
+            .into_iter()
+            .map(|(name, file, place, value)| {
+                // a constant from the command line has no file to point at
+                let (file, line) = if file == COMMAND_LINE {
+                    (String::from("null"), String::from("null"))
+                } else {
+                    (
+                        json_string(file),
+                        line_number(file, place.start).to_string(),
+                    )
+                };
+                format!(
+                    "{{\"name\":{},\"value\":{value},\"file\":{file},\"line\":{line}}}",
+                    json_string(name),
+                )
+            })
+            .collect();
+        let macros: Vec<String> = self
+            .sorted_symbol_macros()
+            .into_iter()
+            .map(|mac| {
+                let params: Vec<String> = mac
+                    .parameters
+                    .iter()
+                    .map(|(_, arg, _)| {
+                        format!(
+                            "{{\"name\":{},\"type\":\"{}\"}}",
+                            json_string(&arg.name),
+                            arg.arg_type.keyword()
+                        )
+                    })
+                    .collect();
+                format!(
+                    "{{\"name\":{},\"parameters\":[{}],\"file\":{},\"line\":{}}}",
+                    json_string(&mac.name.0),
+                    params.join(","),
+                    json_string(&mac.file),
+                    line_number(&mac.file, mac.name.1.start)
+                )
+            })
+            .collect();
+        format!(
+            "{{\"labels\":[{}],\"constants\":[{}],\"macros\":[{}]}}\n",
+            labels.join(","),
+            constants.join(","),
+            macros.join(",")
+        )
+    }
+
+    // labels by address, then by name
+    fn sorted_symbol_labels(&self) -> Vec<(&String, &String, &Range<usize>, usize)> {
+        let mut labels: Vec<_> = self
+            .label_map
+            .iter()
+            .map(|(name, (file, place, address))| (name, file, place, *address & 0xffff))
+            .collect();
+        labels.sort_by(|a, b| (a.3, a.0).cmp(&(b.3, b.0)));
+        labels
+    }
+
+    fn sorted_symbol_constants(&self) -> Vec<(&String, &String, &Range<usize>, i64)> {
+        let mut constants: Vec<_> = self
+            .v_map
+            .iter()
+            .map(|(name, (file, place, value))| (name, file, place, *value))
+            .collect();
+        constants.sort_by(|a, b| a.0.cmp(b.0));
+        constants
+    }
+
+    fn sorted_symbol_macros(&self) -> Vec<&MacroContent> {
+        let mut macros: Vec<_> = self.macro_map.values().map(|(_, mac)| mac).collect();
+        macros.sort_by(|a, b| a.name.0.cmp(&b.name.0));
+        macros
+    }
+}
@@ -1,8 +1,6 @@
 pub mod argument;
 pub mod parser_core;
-pub mod parser_error;
 pub mod parser_macro;
 pub mod passes;
 pub use parser_core::*;
-pub use parser_error::*;
 pub use passes::*;
@@ -1,51 +1,38 @@
 use crate::*;
-use colored::*;
 use logos::Logos;
 use std::iter::Peekable;
 use std::ops::Range;
 use std::vec::IntoIter;
 
 type ParsingLexer = Peekable<IntoIter<(Result<TokenKind, ()>, Range<usize>)>>;
-type ParserResult<'a> = Result<Vec<(String, TokenKind, Range<usize>)>, &'a [ParserError]>;
 
 pub struct Parser<'a> {
     pub file: String,
     pub lexer: ParsingLexer,
     pub input: &'a str,
-    pub errors: Vec<ParserError>,
+    pub errors: Vec<Diagnostic>,
+    pub verbose: bool,
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(file: &String, input: &'a str) -> Result<Self, Vec<(ParserError, bool)>> {
-        let errors = Vec::new();
+    // problems are collected in `errors` rather than stopping the parser, so that
+    // one run reports as much as it can
+    pub fn new(file: &String, input: &'a str, asm: &mut Assembler) -> Self {
         let lexer = TokenKind::lexer(input).spanned();
 
-        let first_pass_tokens = Self::first_pass(file, &String::from(input), lexer);
-        let toks = match first_pass_tokens {
-            Err(e) => return Err(e),
-            Ok(ref v) => v,
-        };
-        let second_pass_tokens = Self::second_pass(
-            &mut Parser {
-                file: file.to_string(),
-                lexer: toks.clone().into_iter().peekable(),
-                input,
-                errors: Vec::new(),
-            },
-            first_pass_tokens?,
-        );
-        let toks = match second_pass_tokens {
-            Err(e) => return Err(e),
-            Ok(ref v) => v,
-        };
-        Ok(Parser {
+        let (first_pass_tokens, errors) = Self::first_pass(file, lexer, asm);
+        let mut parser = Parser {
             file: file.to_string(),
-            lexer: toks.clone().into_iter().peekable(),
+            lexer: Vec::new().into_iter().peekable(),
             input,
             errors,
-        })
+            verbose: asm.options.verbose,
+        };
+        let second_pass_tokens = parser.second_pass(first_pass_tokens);
+        parser.lexer = second_pass_tokens.into_iter().peekable();
+        parser
     }
-    pub fn parse(&mut self) -> ParserResult {
+    pub fn parse(&mut self) -> Vec<(String, TokenKind, Range<usize>)> {
         let mut tokens = Vec::new();
 
         while let Some((token, span)) = self.lexer.next() {
@@ -56,93 +43,44 @@ impl<'a> Parser<'a> {
                     tokens.push((self.file.to_string(), t, span));
                 }
                 Err(()) => {
-                    self.errors.push(ParserError {
-                        file: self.file.to_string(),
-                        help: Some(String::from(
+                    self.errors.push(
+                        Diagnostic::error(
+                            DiagnosticCode::InvalidToken,
+                            "Unknown error encountered whilst parsing".to_string(),
+                        )
+                        .at(&self.file, span.clone())
+                        .with_help(String::from(
                             "this is likely an internal error, please report it",
                         )),
-                        input: self.input.to_string(),
-                        message: "Unknown error encountered whilst parsing".to_string(),
-                        start_pos: span.start,
-                        last_pos: span.end,
-                    });
-                    println!("Token stream {:?}", self.lexer);
+                    );
+                    if self.verbose {
+                        println!("Token stream {:?}", self.lexer);
+                    }
                 }
             }
         }
 
-        if !self.errors.is_empty() {
-            return Err(&self.errors);
-        }
-
-        Ok(tokens)
+        tokens
     }
 }
-pub fn create_parser<'a>(
-    file: &'a str,
-    input_string: &'a str,
-    error_count: &mut i32,
-) -> Option<Parser<'a>> {
-    if CONFIG.verbose {
+pub fn create_parser<'a>(asm: &mut Assembler, file: &'a str, input_string: &'a str) -> Parser<'a> {
+    if asm.options.verbose {
         print_msg!("PARSER CREATION");
     }
-    match Parser::new(&String::from(file), input_string) {
-        Ok(parser) => Some(parser),
-        Err(errors) => {
-            let mut prev_was_chained = false;
-            for (index, (error, chain)) in errors.clone().into_iter().enumerate() {
-                *error_count += 1;
-                if !prev_was_chained {
-                    println!("{error}");
-                }
-                if chain {
-                    prev_was_chained = true;
-                    let (next, _) = errors.get(index + 1).unwrap();
-                    let (num, data) =
-                        highlight_range_in_file(&next.file, &(next.start_pos..next.last_pos));
-                    println!(
-                        "         {}{} in {} {}{} {:^6} {} {}",
-                        "╰".bright_red(),
-                        ">".yellow(),
-                        next.file.green(),
-                        "-".bright_red(),
-                        ">".yellow(),
-                        num.to_string().blue(),
-                        "│".blue(),
-                        data
-                    );
-                    continue;
-                } else {
-                    println!();
-                }
-                prev_was_chained = false;
-            }
-            None
-        }
-    }
+    Parser::new(&String::from(file), input_string, asm)
 }
 
 pub fn parse_tokens(
+    asm: &mut Assembler,
     parser: &mut Parser,
-    _input_string: &str,
-    error_count: &mut i32,
-) -> Option<Vec<(String, TokenKind, Range<usize>)>> {
-    match parser.parse() {
-        Ok(tokens) => {
-            if CONFIG.verbose {
-                print_msg!("INITIAL TOKENS (UNEXPANDED MACROS AND DIRECTIVES)");
-                for (_, element, _) in &tokens {
-                    println!("{}", element);
-                }
-            }
-            Some(tokens)
-        }
-        Err(errors) => {
-            for error in errors {
-                *error_count += 1;
-                println!("{error}");
-            }
-            None
+) -> Vec<(String, TokenKind, Range<usize>)> {
+    let tokens = parser.parse();
+    asm.diagnostics.append(&mut parser.errors);
+    if asm.options.verbose {
+        print_msg!("INITIAL TOKENS (UNEXPANDED MACROS AND DIRECTIVES)");
+        for (_, element, _) in &tokens {
+            println!("{}", element);
         }
     }
+    tokens
 }
@@ -5,7 +5,6 @@ impl Parser<'_> {
         &mut self,
         arg_name: &String,
     ) -> Vec<(String, FullArgument, Range<usize>)> {
-        let input_str = self.input.to_string();
         let (val, loc) = match self.lexer.next() {
             Some((v, l)) => (v, l),
             None => return Vec::new(),
@@ -15,14 +14,16 @@ impl Parser<'_> {
             Ok(TokenKind::Ident(arg_type_str)) => {
                 let mut leave = false;
                 let arg_type = ArgumentType::from_string(&arg_type_str).unwrap_or_else(|| {
-                    self.errors.push(ParserError {
-                        file: self.file.to_string(),
-                        help: Some(String::from("valid argument types are\n         reg, ireg, mem, imem, imm, and label")),
-                        input: input_str,
-                        message: format!("argument type: {} is not valid", arg_type_str),
-                        start_pos: loc.start,
-                        last_pos: loc.end,
-                    });
+                    self.errors.push(
+                        Diagnostic::error(
+                            DiagnosticCode::MacroSyntax,
+                            format!("argument type: {} is not valid", arg_type_str),
+                        )
+                        .at(&self.file, loc.clone())
+                        .with_help(
+                            "valid argument types are\n         reg, ireg, mem, imem, imm, and label",
+                        ),
+                    );
                     leave = true;
                     ArgumentType::Reg
                 });
@@ -39,14 +40,14 @@ impl Parser<'_> {
                 ));
             }
             _ => {
-                self.errors.push(ParserError {
-                    file: self.file.to_string(),
-                    help: Some(String::from("add a type after the ':'")),
-                    input: input_str,
-                    message: "expected argument type".to_string(),
-                    start_pos: loc.start,
-                    last_pos: loc.end,
-                });
+                self.errors.push(
+                    Diagnostic::error(
+                        DiagnosticCode::MacroSyntax,
+                        "expected argument type".to_string(),
+                    )
+                    .at(&self.file, loc.clone())
+                    .with_help(String::from("add a type after the ':'")),
+                );
                 return args;
             }
         }
@@ -57,7 +58,6 @@ impl Parser<'_> {
         &mut self,
         name: &(String, Range<usize>),
     ) -> Vec<(String, TokenKind, Range<usize>)> {
-        let input_str = self.input.to_string();
         let mut tokens = Vec::new();
         let mut args = Vec::new();
         loop {
@@ -74,16 +74,16 @@ impl Parser<'_> {
                 }
                 Ok(TokenKind::RightParen) => break,
                 _ => {
-                    self.errors.push(ParserError {
-                        file: self.file.to_string(),
-                        help: Some(String::from(
+                    self.errors.push(
+                        Diagnostic::error(
+                            DiagnosticCode::MacroSyntax,
+                            "expected a macro argument".to_string(),
+                        )
+                        .at(&self.file, l.clone())
+                        .with_help(String::from(
                             "macro arguments should go between the '(' ')'",
                         )),
-                        input: self.input.to_string(),
-                        message: "expected a macro argument".to_string(),
-                        start_pos: l.start,
-                        last_pos: l.end,
-                    });
+                    );
                     break;
                 }
             }
@@ -106,26 +106,26 @@ impl Parser<'_> {
                                 break;
                             }
                         }
-                        Ok(TokenKind::MacroDef(_)) | Ok(TokenKind::MacroCall(_)) => {
-                            self.errors.push(ParserError {
-                                file: self.file.to_string(),
-                                help: None,
-                                input: self.input.to_string(),
-                                message: "cannot declare or call macro in macro".to_string(),
-                                start_pos: span.start,
-                                last_pos: span.end,
-                            });
+                        // other macros can be called, and are expanded with this one
+                        Ok(TokenKind::MacroDef(_)) => {
+                            self.errors.push(
+                                Diagnostic::error(
+                                    DiagnosticCode::MacroSyntax,
+                                    "cannot declare macro in macro".to_string(),
+                                )
+                                .at(&self.file, span.clone()),
+                            );
                         }
                         Ok(t) => macro_tokens.push((t, span)),
                         _ => {
-                            self.errors.push(ParserError {
-                                file: self.file.to_string(),
-                                help: Some(String::from("close the macro with a '}'")),
-                                input: self.input.to_string(),
-                                message: "error/reached EOF in macro body".to_string(),
-                                start_pos: span.start,
-                                last_pos: span.end,
-                            });
+                            self.errors.push(
+                                Diagnostic::error(
+                                    DiagnosticCode::MacroSyntax,
+                                    "error/reached EOF in macro body".to_string(),
+                                )
+                                .at(&self.file, span.clone())
+                                .with_help(String::from("close the macro with a '}'")),
+                            );
                         }
                     }
                 }
@@ -142,21 +142,19 @@ impl Parser<'_> {
                 ));
             }
             _ => {
-                self.errors.push(ParserError {
-                    file: self.file.to_string(),
-                    help: None,
-                    input: input_str,
-                    message: "did not find open brace for macro body".to_string(),
-                    start_pos: loc.start,
-                    last_pos: loc.end,
-                });
+                self.errors.push(
+                    Diagnostic::error(
+                        DiagnosticCode::MacroSyntax,
+                        "did not find open brace for macro body".to_string(),
+                    )
+                    .at(&self.file, loc.clone()),
+                );
             }
         }
         tokens
     }
 
     pub fn parse_single_macro(&mut self) -> Vec<(String, TokenKind, Range<usize>)> {
-        let input_str = self.input.to_string();
         let mut tokens = Vec::new();
         let (val, loc) = match self.lexer.next() {
             Some((v, l)) => (v, l),
@@ -165,14 +163,14 @@ impl Parser<'_> {
         let name = if let (Ok(TokenKind::Ident(v)), r) = (val, loc.clone()) {
             (v, r)
         } else {
-            self.errors.push(ParserError {
-                file: self.file.to_string(),
-                help: Some(String::from("add a macro name after the declaration")),
-                input: input_str,
-                message: "macro name required".to_string(),
-                start_pos: loc.start,
-                last_pos: loc.end,
-            });
+            self.errors.push(
+                Diagnostic::error(
+                    DiagnosticCode::MacroSyntax,
+                    "macro name required".to_string(),
+                )
+                .at(&self.file, loc.clone())
+                .with_help(String::from("add a macro name after the declaration")),
+            );
             return tokens;
         };
 
@@ -185,14 +183,13 @@ impl Parser<'_> {
                 tokens.extend(self.parse_macro_arguments(&name));
             }
             _ => {
-                self.errors.push(ParserError {
-                    file: self.file.to_string(),
-                    help: None,
-                    input: input_str,
-                    message: "didn't find open parantheses after macro name".to_string(),
-                    start_pos: loc.start,
-                    last_pos: loc.end,
-                });
+                self.errors.push(
+                    Diagnostic::error(
+                        DiagnosticCode::MacroSyntax,
+                        "didn't find open parantheses after macro name".to_string(),
+                    )
+                    .at(&self.file, loc.clone()),
+                );
             }
         }
         tokens
@@ -1,2 +1,4 @@
+pub mod pass_cond;
+pub mod pass_define;
 pub mod pass_one;
 pub mod pass_two;
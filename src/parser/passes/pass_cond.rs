@@ -0,0 +1,223 @@
+use crate::*;
+use std::ops::Range;
+
+type Lexer = Evalex;
+
+// a .if that hasn't reached its .endif yet
+struct OpenBlock {
+    place: Range<usize>,
+    // whether a branch has been assembled already, so no later one can be
+    taken: bool,
+    // whether the branch being read is assembled
+    active: bool,
+    seen_else: bool,
+}
+
+/// The `.if` blocks the first pass is inside of
+///
+/// Conditions are evaluated as the lines are lexed, so constants, macros and
+/// includes in a branch that is left out are never declared or read.
+#[derive(Default)]
+pub struct Conditionals {
+    open: Vec<OpenBlock>,
+}
+
+impl Conditionals {
+    pub fn is_conditional(directive: &str) -> bool {
+        matches!(
+            directive,
+            "if" | "ifdef" | "ifndef" | "elif" | "else" | "endif"
+        )
+    }
+
+    /// Whether the tokens being read are in a branch that is left out
+    pub fn skipping(&self) -> bool {
+        self.open.last().is_some_and(|block| !block.active)
+    }
+
+    /// Handles a conditional directive, reading its operands up to the end of
+    /// the line, and returns what was wrong with it
+    ///
+    /// `tokens` are the ones kept so far, for `.ifdef` to find labels and
+    /// macros declared above it.
+    pub fn directive(
+        &mut self,
+        directive: &str,
+        place: Range<usize>,
+        file: &String,
+        lexer: &mut Lexer,
+        asm: &mut Assembler,
+        tokens: &[(Result<TokenKind, ()>, Range<usize>)],
+    ) -> Vec<Diagnostic> {
+        let mut errors = Vec::new();
+        match directive {
+            "if" | "ifdef" | "ifndef" => {
+                // a block inside a skipped branch is skipped whatever it says
+                let skipped = self.skipping();
+                if skipped {
+                    skip_line(lexer);
+                }
+                let active =
+                    !skipped && condition(directive, &place, file, lexer, asm, tokens, &mut errors);
+                self.open.push(OpenBlock {
+                    place,
+                    taken: active || skipped,
+                    active,
+                    seen_else: false,
+                });
+            }
+            "elif" | "else" => {
+                let Some(block) = self.open.last() else {
+                    errors.push(
+                        Diagnostic::error(
+                            DiagnosticCode::InvalidDirective,
+                            format!(".{directive} without a matching .if"),
+                        )
+                        .at(file, place),
+                    );
+                    skip_line(lexer);
+                    return errors;
+                };
+                if block.seen_else {
+                    errors.push(
+                        Diagnostic::error(
+                            DiagnosticCode::InvalidDirective,
+                            format!(".{directive} cannot come after .else"),
+                        )
+                        .at(file, place)
+                        .with_label(
+                            file,
+                            block.place.clone(),
+                            Some(String::from("in this .if")),
+                        ),
+                    );
+                    skip_line(lexer);
+                    return errors;
+                }
+                let active = if block.taken {
+                    skip_line(lexer);
+                    false
+                } else {
+                    directive == "else"
+                        || condition(directive, &place, file, lexer, asm, tokens, &mut errors)
+                };
+                let block = self.open.last_mut().unwrap();
+                block.active = active;
+                block.taken |= active;
+                block.seen_else = directive == "else";
+            }
+            _ => {
+                if self.open.pop().is_none() {
+                    errors.push(
+                        Diagnostic::error(
+                            DiagnosticCode::InvalidDirective,
+                            String::from(".endif without a matching .if"),
+                        )
+                        .at(file, place),
+                    );
+                }
+            }
+        }
+        if let Some((token, span)) = lexer.next_if(|(t, _)| *t != Ok(TokenKind::Newline)) {
+            let found = match token {
+                Ok(t) => t.to_string(),
+                Err(()) => String::from("an invalid character"),
+            };
+            errors.push(
+                Diagnostic::error(
+                    DiagnosticCode::InvalidDirective,
+                    format!("unexpected {found} after .{directive}"),
+                )
+                .at(file, span),
+            );
+            skip_line(lexer);
+        }
+        errors
+    }
+
+    /// Errors for every block still open at the end of a file
+    pub fn unterminated(self, file: &str) -> Vec<Diagnostic> {
+        self.open
+            .into_iter()
+            .map(|block| {
+                Diagnostic::error(
+                    DiagnosticCode::InvalidDirective,
+                    String::from("this .if has no .endif"),
+                )
+                .at(file, block.place)
+                .with_help("close it with .endif before the end of the file")
+            })
+            .collect()
+    }
+}
+
+// whether the branch after a .if, .ifdef, .ifndef or .elif is assembled
+fn condition(
+    directive: &str,
+    place: &Range<usize>,
+    file: &String,
+    lexer: &mut Lexer,
+    asm: &mut Assembler,
+    tokens: &[(Result<TokenKind, ()>, Range<usize>)],
+    errors: &mut Vec<Diagnostic>,
+) -> bool {
+    if directive == "if" || directive == "elif" {
+        return match evaluate_expression(file, lexer, asm) {
+            Ok(value) => value != 0,
+            Err(e) => {
+                errors.push(e);
+                skip_line(lexer);
+                false
+            }
+        };
+    }
+    let Some((Ok(TokenKind::Ident(name)), _)) =
+        lexer.next_if(|(t, _)| matches!(t, Ok(TokenKind::Ident(_))))
+    else {
+        errors.push(
+            Diagnostic::error(
+                DiagnosticCode::InvalidDirective,
+                format!(".{directive} must be succeeded by a name"),
+            )
+            .at(file, place.clone()),
+        );
+        return false;
+    };
+    let declared_above = tokens
+        .iter()
+        .enumerate()
+        .any(|(i, (token, _))| match token {
+            Ok(TokenKind::Label(label)) => *label == name,
+            Ok(TokenKind::MacroDef(_)) => {
+                matches!(tokens.get(i + 1), Some((Ok(TokenKind::Ident(m)), _)) if *m == name)
+            }
+            Ok(TokenKind::Included(included)) => declared_in(included, &name),
+            _ => false,
+        });
+    let constant = asm.v_map.contains_key(&name);
+    let defined = constant
+        || declared_above
+        || asm.label_map.contains_key(&name)
+        || asm.macro_map.contains_key(&name);
+    if constant {
+        asm.uses.constants.insert(name);
+    }
+    defined == (directive == "ifdef")
+}
+
+// whether the parsed tokens of an included file declare a label or macro `name`
+fn declared_in(tokens: &[(String, TokenKind, Range<usize>)], name: &str) -> bool {
+    tokens.iter().any(|(_, token, _)| match token {
+        TokenKind::Label(label) => label == name,
+        TokenKind::Macro(data) => data.name.0 == name,
+        TokenKind::Included(included) => declared_in(included, name),
+        _ => false,
+    })
+}
+
+fn skip_line(lexer: &mut Lexer) {
+    while lexer
+        .next_if(|(t, _)| *t != Ok(TokenKind::Newline))
+        .is_some()
+    {}
+}
@@ -0,0 +1,164 @@
+use crate::misc::*;
+use crate::*;
+use colored::*;
+use std::ops::Range;
+
+type Tokens = Vec<(Result<TokenKind, ()>, Range<usize>)>;
+
+impl Parser<'_> {
+    /// Reads `@define` and `@undef` lines, and replaces `$NAME` and bare
+    /// names of defines with their replacement tokens
+    ///
+    /// The first pass hands each line over before it folds expressions or
+    /// evaluates conditions, so a replacement is made of the same tokens as
+    /// any other line and can be part of an expression, an address, or a
+    /// whole instruction. The tokens put in take the place of the name that
+    /// was replaced, so errors in them point at where the define was used.
+    pub fn define_pass(
+        file: &String,
+        tokens: Tokens,
+        asm: &mut Assembler,
+    ) -> (Tokens, Vec<Diagnostic>) {
+        let mut new_tokens = Vec::new();
+        let mut errors = Vec::new();
+        let mut token_iter = tokens.into_iter().peekable();
+        while let Some((token, span)) = token_iter.next() {
+            match token {
+                Ok(TokenKind::Define(name)) => {
+                    let mut replacement = Vec::new();
+                    while let Some((token, place)) =
+                        token_iter.next_if(|(t, _)| *t != Ok(TokenKind::Newline))
+                    {
+                        // defines used in a replacement are replaced when it is read
+                        match token.map(|t| replace(file, t, &place, asm)) {
+                            Ok(Ok(toks)) => replacement.extend(toks),
+                            Ok(Err(e)) => errors.push(e),
+                            Err(()) => errors.push(
+                                Diagnostic::error(
+                                    DiagnosticCode::DefineSyntax,
+                                    String::from("invalid token in @define"),
+                                )
+                                .at(file, place),
+                            ),
+                        }
+                    }
+                    if name.is_empty() {
+                        errors.push(missing_name(file, span, "@define"));
+                    } else if let Some((f, s, _)) = asm.defines.get(&name) {
+                        errors.push(
+                            Diagnostic::error(
+                                DiagnosticCode::DuplicateDefine,
+                                format!("`{}` was defined twice", name.magenta()),
+                            )
+                            .at(file, span)
+                            .with_declaration(f, s.clone(), "previous definition here")
+                            .with_help(format!("use `@undef {name}` before defining it again")),
+                        );
+                    } else {
+                        asm.defines
+                            .insert(name, (file.to_string(), span, replacement));
+                    }
+                }
+                Ok(TokenKind::Undef(name)) => {
+                    if name.is_empty() {
+                        errors.push(missing_name(file, span, "@undef"));
+                    } else {
+                        asm.defines.remove(&name);
+                    }
+                    if let Some((_, place)) =
+                        token_iter.next_if(|(t, _)| *t != Ok(TokenKind::Newline))
+                    {
+                        errors.push(
+                            Diagnostic::error(
+                                DiagnosticCode::DefineSyntax,
+                                String::from("@undef takes only a name"),
+                            )
+                            .at(file, place),
+                        );
+                        while token_iter
+                            .next_if(|(t, _)| *t != Ok(TokenKind::Newline))
+                            .is_some()
+                        {}
+                    }
+                }
+                // a label or parameter declared with the name of a define
+                Ok(TokenKind::Ident(name))
+                    if matches!(token_iter.peek(), Some((Ok(TokenKind::Colon), _))) =>
+                {
+                    new_tokens.push((Ok(TokenKind::Ident(name)), span));
+                }
+                Ok(token) => match replace(file, token, &span, asm) {
+                    Ok(toks) => new_tokens.extend(toks.into_iter().map(|t| (Ok(t), span.clone()))),
+                    Err(e) => {
+                        // the rest of the line would only give more errors
+                        errors.push(e);
+                        let line_start = new_tokens
+                            .iter()
+                            .rposition(|(t, _)| *t == Ok(TokenKind::Newline))
+                            .map_or(0, |i| i + 1);
+                        new_tokens.truncate(line_start);
+                        while token_iter
+                            .next_if(|(t, _)| *t != Ok(TokenKind::Newline))
+                            .is_some()
+                        {}
+                    }
+                },
+                Err(()) => new_tokens.push((Err(()), span)),
+            }
+        }
+        (new_tokens, errors)
+    }
+}
+
+// the tokens `token` stands for, once defines are replaced
+fn replace(
+    file: &str,
+    token: TokenKind,
+    place: &Range<usize>,
+    asm: &Assembler,
+) -> Result<Vec<TokenKind>, Diagnostic> {
+    match token {
+        TokenKind::DefineCall(name) => match asm.defines.get(&name) {
+            Some((_, _, replacement)) => Ok(replacement.clone()),
+            None => Err(unknown_define(file, place, &name, asm)),
+        },
+        TokenKind::Ident(name) if asm.defines.contains_key(&name) => {
+            Ok(asm.defines[&name].2.clone())
+        }
+        token => Ok(vec![token]),
+    }
+}
+
+fn unknown_define(file: &str, place: &Range<usize>, name: &str, asm: &Assembler) -> Diagnostic {
+    let mut similar: Vec<&String> = asm
+        .defines
+        .keys()
+        .filter(|key| levenshtein(name, key) <= 3)
+        .collect();
+    similar.sort();
+    let error = Diagnostic::error(
+        DiagnosticCode::UnknownDefine,
+        format!("`{}` is not defined", name.magenta()),
+    )
+    .at(file, place.clone());
+    if similar.is_empty() {
+        error.with_help(format!("define it above with `@define {name} ...`"))
+    } else {
+        error.with_help(format!(
+            "similar defines were found: {}",
+            similar
+                .iter()
+                .map(|s| s.magenta().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+    }
+}
+
+fn missing_name(file: &str, place: Range<usize>, directive: &str) -> Diagnostic {
+    Diagnostic::error(
+        DiagnosticCode::DefineSyntax,
+        format!("{directive} must be succeeded by a name"),
+    )
+    .at(file, place)
+}
@@ -1,247 +1,348 @@
+use super::pass_cond::Conditionals;
 use crate::*;
 use colored::*;
-use std::ops::Range; // chain
-type PassResult = Result<Vec<(Result<TokenKind, ()>, Range<usize>)>, Vec<(ParserError, bool)>>;
+use std::ops::Range;
+type Lexed = (Result<TokenKind, ()>, Range<usize>);
+// the tokens that could be read, and the errors for the ones that couldn't
+type PassResult = (Vec<Lexed>, Vec<Diagnostic>);
 impl<'a> Parser<'a> {
     pub fn first_pass(
         file: &String,
-        input: &String,
-        lexer: logos::SpannedIter<'a, TokenKind>,
+        mut source: logos::SpannedIter<'a, TokenKind>,
+        asm: &mut Assembler,
     ) -> PassResult {
         let mut tokens = Vec::new();
-        let mut lexer = lexer.peekable();
         let mut errors = Vec::new();
         let mut const_names = Vec::new();
         let mut prev_was_const = false;
         let mut saw_amp = false;
         let mut cspan = 0..0; // (C)onstant span
-        while let Some((token, span)) = lexer.next() {
-            match token {
-                Ok(TokenKind::Amp) => saw_amp = true,
-                Ok(TokenKind::MacroDef(m)) => {
-                    saw_amp = false;
-                    // this here to make sure leftparen doesn't
-                    // accidentally start reading a macro
-                    prev_was_const = false;
-                    tokens.push((Ok(TokenKind::MacroDef(m)), span));
-                    'mdl: loop {
-                        match lexer.next() {
-                            Some((Ok(TokenKind::LeftBrace), l)) => {
-                                tokens.push((Ok(TokenKind::LeftBrace), l));
-                                break 'mdl;
+        let mut conditionals = Conditionals::default();
+        // a line at a time, so each is read with the defines declared above it
+        while let Some(mut line) = next_line(&mut source) {
+            if !conditionals.skipping() {
+                errors.extend(too_large_literals(file, &mut line, source.source()));
+            }
+            let line = if conditionals.skipping() && !is_conditional(&line) {
+                line
+            } else {
+                let (line, define_errors) = Self::define_pass(file, line, asm);
+                // a branch left out can't have anything wrong with it
+                if !conditionals.skipping() {
+                    errors.extend(define_errors);
+                }
+                line
+            };
+            let mut lexer = line.into_iter().peekable();
+            while let Some((token, span)) = lexer.next() {
+                if let Ok(TokenKind::Directive(d)) = &token {
+                    if Conditionals::is_conditional(d) {
+                        errors.extend(
+                            conditionals.directive(d, span, file, &mut lexer, asm, &tokens),
+                        );
+                        continue;
+                    }
+                }
+                if conditionals.skipping() {
+                    continue;
+                }
+                match token {
+                    // the count of a .rept is read here, where a whole expression can be
+                    // evaluated, and handed on as one literal
+                    Ok(TokenKind::Directive(d)) if d == "rept" => {
+                        saw_amp = false;
+                        prev_was_const = false;
+                        tokens.push((Ok(TokenKind::Directive(d)), span.clone()));
+                        let place = lexer.peek().map_or(span.clone(), |(_, s)| s.clone());
+                        let missing =
+                            matches!(lexer.peek(), None | Some((Ok(TokenKind::Newline), _)));
+                        let count = match evaluate_expression(file, &mut lexer, asm) {
+                            _ if missing => {
+                                errors.push(
+                                    Diagnostic::error(
+                                        DiagnosticCode::InvalidDirective,
+                                        String::from(
+                                            ".rept directive must be succeeded by a count",
+                                        ),
+                                    )
+                                    .at(file, span),
+                                );
+                                0
                             }
-                            Some((Ok(TokenKind::Ident(ident)), span)) => {
-                                if let Some((Ok(TokenKind::Colon), _)) = lexer.peek() {
-                                    let (_, _) = lexer.next().unwrap();
-                                    tokens.push((Ok(TokenKind::Label(ident)), span));
-                                } else {
-                                    tokens.push((Ok(TokenKind::Ident(ident)), span));
+                            Ok(count) => {
+                                if let Some((token, at)) =
+                                    lexer.next_if(|(t, _)| *t != Ok(TokenKind::Newline))
+                                {
+                                    let found = match token {
+                                        Ok(t) => t.to_string(),
+                                        Err(()) => String::from("an invalid character"),
+                                    };
+                                    errors.push(
+                                        Diagnostic::error(
+                                            DiagnosticCode::InvalidExpression,
+                                            format!("unexpected {found} after the .rept count"),
+                                        )
+                                        .at(file, at),
+                                    );
                                 }
+                                count
                             }
-                            Some(v) => tokens.push(v),
-                            _ => break 'mdl,
-                        }
+                            Err(e) => {
+                                errors.push(e);
+                                0
+                            }
+                        };
+                        // an invalid count leaves the block out, having been reported
+                        while lexer
+                            .next_if(|(t, _)| *t != Ok(TokenKind::Newline))
+                            .is_some()
+                        {}
+                        tokens.push((Ok(TokenKind::IntLit(count)), place));
                     }
-                }
-                Ok(TokenKind::Constant(name)) => {
-                    saw_amp = false;
-                    const_names.push(name);
-                    prev_was_const = true;
-                    cspan = span.clone();
-                    if let Some((Ok(TokenKind::Equal), _)) = lexer.peek() {
-                        lexer.next();
-                    } else {
-                        errors.push((
-                            ParserError {
-                                file: file.to_string(),
-                                help: None,
-                                input: input.to_string(),
-                                message: String::from(
-                                    "constant requires equal sign to denote assignment",
-                                ),
-                                start_pos: span.start,
-                                last_pos: span.end,
-                            },
-                            false,
-                        ));
+                    // read here rather than after this file, so the included one sees
+                    // the defines above the @include and the lines below see its constants
+                    Ok(TokenKind::IncludeFile(path)) => {
+                        saw_amp = false;
+                        prev_was_const = false;
+                        match asm.include_file(file, &span, &path) {
+                            Ok(included) => tokens.push((Ok(TokenKind::Included(included)), span)),
+                            Err(e) => errors.push(e),
+                        }
                     }
-                }
-                Ok(TokenKind::Ident(ident)) => {
-                    saw_amp = false;
-                    if let Some((Ok(TokenKind::Colon), _)) = lexer.peek() {
-                        let (_, _) = lexer.next().unwrap();
-                        tokens.push((Ok(TokenKind::Label(ident)), span));
-                    } else {
-                        tokens.push((Ok(TokenKind::Ident(ident)), span));
+                    Ok(TokenKind::Amp) => saw_amp = true,
+                    Ok(TokenKind::MacroDef(m)) => {
+                        saw_amp = false;
+                        // this here to make sure leftparen doesn't
+                        // accidentally start reading a macro
+                        prev_was_const = false;
+                        tokens.push((Ok(TokenKind::MacroDef(m)), span));
+                        'mdl: loop {
+                            match lexer.next() {
+                                Some((Ok(TokenKind::LeftBrace), l)) => {
+                                    tokens.push((Ok(TokenKind::LeftBrace), l));
+                                    break 'mdl;
+                                }
+                                Some((Ok(TokenKind::Ident(ident)), span)) => {
+                                    if let Some((Ok(TokenKind::Colon), _)) = lexer.peek() {
+                                        let (_, _) = lexer.next().unwrap();
+                                        tokens.push((Ok(TokenKind::Label(ident)), span));
+                                    } else {
+                                        tokens.push((Ok(TokenKind::Ident(ident)), span));
+                                    }
+                                }
+                                Some(v) => tokens.push(v),
+                                _ => break 'mdl,
+                            }
+                        }
                     }
-                    prev_was_const = false;
-                }
-                Ok(TokenKind::IntLit(v)) => {
-                    saw_amp = false;
-                    if prev_was_const {
-                        if let Some(n) = const_names.pop() {
-                            check_vmap(span, file, input, &n)?;
-                            let mut vmap = V_MAP.lock().unwrap();
-                            vmap.insert(n, (file.to_string(), cspan.clone(), v));
-                            std::mem::drop(vmap);
+                    Ok(TokenKind::Constant(name)) => {
+                        saw_amp = false;
+                        const_names.push(name);
+                        prev_was_const = true;
+                        cspan = span.clone();
+                        if let Some((Ok(TokenKind::Equal), _)) = lexer.peek() {
+                            lexer.next();
                         } else {
-                            errors.push((
-                                ParserError {
-                                    file: file.to_string(),
-                                    help: None,
-                                    input: input.to_string(),
-                                    message: String::from(
-                                        "could not find associated constant for literal",
+                            errors.push(
+                                Diagnostic::error(
+                                    DiagnosticCode::ConstantSyntax,
+                                    String::from(
+                                        "constant requires equal sign to denote assignment",
                                     ),
-                                    start_pos: span.start,
-                                    last_pos: span.end,
-                                },
-                                false,
-                            ));
+                                )
+                                .at(file, span.clone()),
+                            );
                         }
-                    } else {
-                        tokens.push((Ok(TokenKind::IntLit(v)), span));
                     }
-                    prev_was_const = false;
-                }
-                Ok(TokenKind::LeftBracket) => {
-                    // memory addresses are also not instructions
-                    prev_was_const = false;
-                    let mut addr_toks = Vec::new();
-                    'mdl: loop {
-                        match lexer.next() {
-                            // let's try to do math in it
-                            Some((Ok(TokenKind::LeftParen), span)) => {
-                                match parse_expression_after_left_paren(file, input, &mut lexer) {
-                                    Ok(Some((value, new_span))) => {
-                                        addr_toks.push((TokenKind::IntLit(value), new_span));
-                                    }
-                                    Ok(None) => {
-                                        addr_toks.push((TokenKind::LeftParen, span));
-                                        break 'mdl;
-                                    }
-                                    Err(e) => {
-                                        errors.push((e, false));
+                    Ok(TokenKind::Ident(ident)) => {
+                        saw_amp = false;
+                        if let Some((Ok(TokenKind::Colon), _)) = lexer.peek() {
+                            let (_, _) = lexer.next().unwrap();
+                            tokens.push((Ok(TokenKind::Label(ident)), span));
+                        } else {
+                            tokens.push((Ok(TokenKind::Ident(ident)), span));
+                        }
+                        prev_was_const = false;
+                    }
+                    Ok(TokenKind::IntLit(v)) => {
+                        saw_amp = false;
+                        if prev_was_const {
+                            if let Some(n) = const_names.pop() {
+                                match check_vmap(span, file, &n, &asm.v_map) {
+                                    Some(e) => errors.push(e),
+                                    None => {
+                                        asm.v_map.insert(n, (file.to_string(), cspan.clone(), v));
                                     }
                                 }
+                            } else {
+                                errors.push(
+                                    Diagnostic::error(
+                                        DiagnosticCode::ConstantSyntax,
+                                        String::from(
+                                            "could not find associated constant for literal",
+                                        ),
+                                    )
+                                    .at(file, span.clone()),
+                                );
                             }
-                            Some((Ok(TokenKind::RightBracket), _)) => {
-                                break 'mdl;
-                            }
-                            Some((Ok(TokenKind::Ident(ident)), span)) => {
-                                if let Some((Ok(TokenKind::Colon), _)) = lexer.peek() {
-                                    let (_, _) = lexer.next().unwrap();
-                                    addr_toks.push(((TokenKind::Label(ident)), span));
-                                } else {
-                                    addr_toks.push(((TokenKind::Ident(ident)), span));
+                        } else {
+                            tokens.push((Ok(TokenKind::IntLit(v)), span));
+                        }
+                        prev_was_const = false;
+                    }
+                    Ok(TokenKind::LeftBracket) => {
+                        // memory addresses are also not instructions
+                        prev_was_const = false;
+                        let mut addr_toks = Vec::new();
+                        'mdl: loop {
+                            match lexer.next() {
+                                // let's try to do math in it
+                                Some((Ok(TokenKind::LeftParen), span)) => {
+                                    match parse_expression_after_left_paren(file, &mut lexer, asm) {
+                                        Ok(Some((value, new_span))) => {
+                                            addr_toks.push((TokenKind::IntLit(value), new_span));
+                                        }
+                                        Ok(None) => {
+                                            addr_toks.push((TokenKind::LeftParen, span));
+                                            break 'mdl;
+                                        }
+                                        Err(e) => {
+                                            errors.push(e);
+                                        }
+                                    }
+                                }
+                                Some((Ok(TokenKind::RightBracket), _)) => {
+                                    break 'mdl;
                                 }
+                                Some((Ok(TokenKind::Ident(ident)), span)) => {
+                                    if let Some((Ok(TokenKind::Colon), _)) = lexer.peek() {
+                                        let (_, _) = lexer.next().unwrap();
+                                        addr_toks.push(((TokenKind::Label(ident)), span));
+                                    } else {
+                                        addr_toks.push(((TokenKind::Ident(ident)), span));
+                                    }
+                                }
+                                Some((Ok(TokenKind::RightParen), _)) => (),
+                                Some((Ok(v), span)) => addr_toks.push((v, span)),
+                                _ => break 'mdl,
                             }
-                            Some((Ok(TokenKind::RightParen), _)) => (),
-                            Some((Ok(v), span)) => addr_toks.push((v, span)),
-                            _ => break 'mdl,
                         }
+                        tokens.push((
+                            Ok(TokenKind::Mem(MemAddr {
+                                indirect: saw_amp,
+                                data: addr_toks,
+                            })),
+                            span,
+                        ));
+                        saw_amp = false;
                     }
-                    tokens.push((
-                        Ok(TokenKind::Mem(MemAddr {
-                            indirect: saw_amp,
-                            data: addr_toks,
-                        })),
-                        span,
-                    ));
-                    saw_amp = false;
-                }
-                Ok(TokenKind::LeftParen) => 'lpn: {
-                    saw_amp = false;
-                    match parse_expression_after_left_paren(file, input, &mut lexer) {
-                        Ok(Some((value, new_span))) => {
-                            if prev_was_const {
-                                if let Some(n) = const_names.pop() {
-                                    check_vmap(span, file, input, &n)?;
-                                    let mut vmap = V_MAP.lock().unwrap();
-                                    vmap.insert(n, (file.to_string(), cspan.clone(), value));
-                                    std::mem::drop(vmap);
+                    Ok(TokenKind::LeftParen) => 'lpn: {
+                        saw_amp = false;
+                        match parse_expression_after_left_paren(file, &mut lexer, asm) {
+                            Ok(Some((value, new_span))) => {
+                                if prev_was_const {
+                                    if let Some(n) = const_names.pop() {
+                                        match check_vmap(span, file, &n, &asm.v_map) {
+                                            Some(e) => errors.push(e),
+                                            None => {
+                                                asm.v_map.insert(
+                                                    n,
+                                                    (file.to_string(), cspan.clone(), value),
+                                                );
+                                            }
+                                        }
+                                    } else {
+                                        errors.push(
+                                            Diagnostic::error(
+                                                DiagnosticCode::ConstantSyntax,
+                                                String::from(
+                                                    "could not find associated constant name",
+                                                ),
+                                            )
+                                            .at(file, new_span.clone()),
+                                        );
+                                    }
                                 } else {
-                                    errors.push((
-                                        ParserError {
-                                            file: file.to_string(),
-                                            help: None,
-                                            input: input.to_string(),
-                                            message: String::from(
-                                                "could not find associated constant name",
-                                            ),
-                                            start_pos: new_span.start,
-                                            last_pos: new_span.end,
-                                        },
-                                        false,
-                                    ));
+                                    tokens.push((Ok(TokenKind::IntLit(value)), new_span));
                                 }
-                            } else {
-                                tokens.push((Ok(TokenKind::IntLit(value)), new_span));
+                            }
+                            Ok(None) => {
+                                tokens.push((Ok(TokenKind::LeftParen), span));
+                                break 'lpn;
+                            }
+                            Err(e) => {
+                                errors.push(e);
                             }
                         }
-                        Ok(None) => {
-                            tokens.push((Ok(TokenKind::LeftParen), span));
-                            break 'lpn;
-                        }
-                        Err(e) => {
-                            errors.push((e, false));
+                        if let Some((Ok(TokenKind::RightParen), _)) = lexer.peek() {
+                            lexer.next();
                         }
+                        prev_was_const = false;
                     }
-                    if let Some((Ok(TokenKind::RightParen), _)) = lexer.peek() {
-                        lexer.next();
+                    _ => {
+                        saw_amp = false;
+                        tokens.push((token, span));
                     }
-                    prev_was_const = false;
-                }
-                _ => {
-                    saw_amp = false;
-                    tokens.push((token, span));
                 }
             }
         }
 
-        if !errors.is_empty() {
-            return Err(errors);
+        errors.extend(conditionals.unterminated(file));
+        (tokens, errors)
+    }
+}
+
+// the tokens up to and including the next newline
+fn next_line(source: &mut logos::SpannedIter<TokenKind>) -> Option<Vec<Lexed>> {
+    let mut line = Vec::new();
+    for (token, span) in source.by_ref() {
+        let end = token == Ok(TokenKind::Newline);
+        line.push((token, span));
+        if end {
+            break;
         }
-        Ok(tokens)
     }
+    (!line.is_empty()).then_some(line)
 }
 
-fn check_vmap(
-    span: Range<usize>,
-    file: &String,
-    input: &String,
-    n: &str,
-) -> Result<(), Vec<(ParserError, bool)>> {
-    let vmap = V_MAP.lock().unwrap();
+// reports every literal in `line` too large for 64 bits, reading it as 0 so
+// the rest of the line only gives the errors it would anyway
+fn too_large_literals(file: &str, line: &mut [Lexed], source: &str) -> Vec<Diagnostic> {
     let mut errors = Vec::new();
-    if let Some((f, s, _)) = vmap.get(n) {
-        errors.push((
-            ParserError {
-                file: file.to_string(),
-                help: Some(format!("{} previous declaration here", "╮".bright_red())),
-                input: input.to_string(),
-                message: format!("constant `{}` was declared twice", n.magenta()),
-                start_pos: span.start,
-                last_pos: span.end,
-            },
-            true,
-        ));
-        errors.push((
-            ParserError {
-                file: f.to_string(),
-                help: None,
-                input: read_file(f),
-                message: String::new(),
-                start_pos: s.start,
-                last_pos: s.end,
-            },
-            false,
-        ));
-        std::mem::drop(vmap);
-    }
-    if !errors.is_empty() {
-        Err(errors)
-    } else {
-        Ok(())
+    for (token, span) in line {
+        let text = &source[span.clone()];
+        // nothing else that can't be lexed starts with a digit
+        if token.is_err()
+            && text
+                .trim_start_matches('-')
+                .starts_with(|c: char| c.is_ascii_digit())
+        {
+            errors.push(
+                Diagnostic::error(
+                    DiagnosticCode::InvalidToken,
+                    format!("integer literal `{}` is too large", text.magenta()),
+                )
+                .at(file, span.clone())
+                .with_help("literals must fit in 64 bits"),
+            );
+            *token = Ok(TokenKind::IntLit(0));
+        }
     }
+    errors
+}
+
+fn is_conditional(line: &[Lexed]) -> bool {
+    matches!(line.first(), Some((Ok(TokenKind::Directive(d)), _)) if Conditionals::is_conditional(d))
+}
+
+fn check_vmap(span: Range<usize>, file: &str, n: &str, vmap: &ConstMap) -> Option<Diagnostic> {
+    let (f, s, _) = vmap.get(n)?;
+    Some(
+        Diagnostic::error(
+            DiagnosticCode::DuplicateConstant,
+            format!("constant `{}` was declared twice", n.magenta()),
+        )
+        .at(file, span)
+        .with_declaration(f, s.clone(), "previous declaration here"),
+    )
 }
@@ -1,14 +1,14 @@
 use crate::*;
 use std::ops::Range;
-type PassResult = Result<Vec<(Result<TokenKind, ()>, Range<usize>)>, Vec<(ParserError, bool)>>;
 impl Parser<'_> {
+    // an invalid instruction is reported and left out, and the pass carries on from
+    // the next line - its operands have already been consumed up to the newline
     pub fn second_pass(
         &mut self,
         tokens: Vec<(Result<TokenKind, ()>, Range<usize>)>,
-    ) -> PassResult {
+    ) -> Vec<(Result<TokenKind, ()>, Range<usize>)> {
         let mut new_tokens = Vec::new();
         let mut token_iter = tokens.clone().into_iter().peekable();
-        let mut errors = Vec::new();
         let mut iter_count = 0;
         while let Some((token, span)) = token_iter.next() {
             iter_count += 1;
@@ -19,6 +19,8 @@ impl Parser<'_> {
                     'mdl: loop {
                         match token_iter.next() {
                             Some((Ok(TokenKind::Newline), l)) => {
+                                // counted here, as the break skips the count below
+                                iter_count += 1;
                                 new_tokens.push((Ok(TokenKind::Newline), l));
                                 break 'mdl;
                             }
@@ -86,6 +88,7 @@ impl Parser<'_> {
                         }
                         let ins = InstructionData {
                             expanded: false,
+                            expansion: Vec::new(),
                             name: name.to_string(),
                             operands: args.clone(),
                             location: span.clone(),
@@ -99,38 +102,30 @@ impl Parser<'_> {
                                 Some(ref v) => v.end,
                                 None => span.end,
                             };
-                            errors.push((
-                                ParserError {
-                                    file: self.file.to_string(),
-                                    help: f.2,
-                                    input: self.input.to_string(),
-                                    message: f.1.to_string(),
-                                    start_pos: start,
-                                    last_pos: end,
-                                },
-                                false,
-                            ));
-                            return Err(errors);
+                            self.errors.push(
+                                Diagnostic::error(
+                                    DiagnosticCode::InvalidInstruction,
+                                    f.1.to_string(),
+                                )
+                                .at(&self.file, start..end)
+                                .with_help_opt(f.2),
+                            );
+                            continue;
                         }
                         if args.len() > 3 {
-                            errors.push((
-                                ParserError {
-                                    file: self.file.to_string(),
-                                    help: None,
-                                    input: self.input.to_string(),
-                                    message: format!(
-                                        "instructions cannot have {} arguments",
-                                        args.len()
-                                    ),
-                                    start_pos: span.start,
-                                    last_pos: span.end,
-                                },
-                                false,
-                            ));
+                            self.errors.push(
+                                Diagnostic::error(
+                                    DiagnosticCode::InvalidInstruction,
+                                    format!("instructions cannot have {} arguments", args.len()),
+                                )
+                                .at(&self.file, span.clone()),
+                            );
+                            continue;
                         }
                         new_tokens.push((
                             Ok(TokenKind::Instruction(InstructionData {
                                 expanded: false,
+                                expansion: Vec::new(),
                                 name,
                                 operands: args,
                                 location: span.clone(),
@@ -139,14 +134,21 @@ impl Parser<'_> {
                         ));
                     }
                 }
+                Ok(TokenKind::Directive(d)) => {
+                    new_tokens.push((Ok(TokenKind::Directive(d)), span));
+                    // names after a directive are its operands, not instructions
+                    while let Some(operand) =
+                        token_iter.next_if(|(t, _)| !matches!(t, Ok(TokenKind::Newline)))
+                    {
+                        new_tokens.push(operand);
+                        iter_count += 1;
+                    }
+                }
                 _ => {
                     new_tokens.push((token, span));
                 }
             }
         }
-        if !errors.is_empty() {
-            return Err(errors);
-        }
-        Ok(new_tokens)
+        new_tokens
     }
 }
@@ -1,208 +1,725 @@
 use crate::*;
 use colored::*;
+use std::cell::Cell;
+use std::iter::Peekable;
 use std::ops::Range;
 
-pub fn process_start(toks: &mut Vec<(String, TokenKind, Range<usize>)>, error_count: &mut i32) {
-    use crate::TokenKind::*;
-    let mut toks_iter = toks.clone().into_iter().peekable();
-    let mut start_addr = 100;
-    let mut seen_start = false;
-    while let Some((fname, tok, span)) = toks_iter.next() {
-        if let Directive(data) = tok {
-            if data.as_str() == "start" {
-                if seen_start {
-                    handle_core_error(
-                        &fname,
-                        &span,
-                        error_count,
-                        ".start directive can only be declared once",
-                        None,
-                    );
-                    break;
-                }
-                if let Some((_, TokenKind::IntLit(val), _)) = toks_iter.peek() {
-                    start_addr = *val;
-                    let mut st_gl = START_LOCATION.lock().unwrap();
-                    *st_gl = *val;
-                    seen_start = true;
-                } else if let Some((_, TokenKind::Mem(addr), _)) = toks_iter.peek() {
-                    if let Some((val, _)) = addr.data.first() {
-                        start_addr = val.get_value();
-                        let mut st_gl = START_LOCATION.lock().unwrap();
-                        *st_gl = val.get_value();
+// one past the last address a word can be placed at
+const MEMORY_END: i64 = 0x10000;
+
+impl Assembler {
+    pub fn process_start(&mut self, toks: &mut Vec<(String, TokenKind, Range<usize>)>) {
+        use crate::TokenKind::*;
+        let mut toks_iter = toks.clone().into_iter().peekable();
+        let mut start_addr = 100;
+        let mut seen_start = false;
+        while let Some((fname, tok, span)) = toks_iter.next() {
+            if let Directive(data) = tok {
+                if data.as_str() == "start" {
+                    if seen_start {
+                        handle_core_error(
+                            &fname,
+                            &span,
+                            &mut self.diagnostics,
+                            DiagnosticCode::InvalidDirective,
+                            ".start directive can only be declared once",
+                            None,
+                        );
+                        continue;
+                    }
+                    if let Some((_, TokenKind::IntLit(val), _)) = toks_iter.peek() {
+                        start_addr = *val;
+                        self.start_location = *val;
                         seen_start = true;
+                    } else if let Some((_, TokenKind::Mem(addr), _)) = toks_iter.peek() {
+                        if let Some((val, _)) = addr.data.first() {
+                            start_addr = val.get_value();
+                            self.start_location = val.get_value();
+                            seen_start = true;
+                        } else {
+                            handle_core_error(
+                                &fname,
+                                &span,
+                                &mut self.diagnostics,
+                                DiagnosticCode::InvalidDirective,
+                                ".start directive must be succeeded by memory literal",
+                                None,
+                            );
+
+                            continue;
+                        }
                     } else {
                         handle_core_error(
                             &fname,
                             &span,
-                            error_count,
-                            ".start directive must be succeeded by memory literal",
+                            &mut self.diagnostics,
+                            DiagnosticCode::InvalidDirective,
+                            ".start directive must be succeeded by memory address",
                             None,
                         );
 
-                        break;
+                        continue;
                     }
-                } else {
-                    handle_core_error(
-                        &fname,
-                        &span,
-                        error_count,
-                        ".start directive must be succeeded by memory address",
-                        None,
-                    );
-
-                    break;
                 }
             }
         }
-    }
-    process_directives(toks, error_count, start_addr);
-    let mut new_toks = Vec::new();
-    {
-        for (fname, tok, span) in &mut *toks {
-            if let TokenKind::Label(_) = tok {
-            } else {
-                new_toks.push((fname.to_string(), tok.clone(), span.clone()));
+        // a start address given through the options wins over .start
+        if let Some(addr) = self.options.start_address {
+            start_addr = addr;
+            self.start_location = addr;
+        }
+        // objects are placed by the linker, so their labels count from 0
+        if self.options.output_format == OutputFormat::Object {
+            if seen_start || self.options.start_address.is_some() {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        DiagnosticCode::InvalidDirective,
+                        String::from("an object file has no start address"),
+                    )
+                    .with_help("pass `--base` to basm-link to choose where the program goes"),
+                );
             }
+            start_addr = 0;
+            self.start_location = 0;
         }
-    }
-    *toks = new_toks;
-}
-
-fn process_directives(
-    toks: &mut [(String, TokenKind, Range<usize>)],
-    error_count: &mut i32,
-    start_addr: i64,
-) {
-    use crate::TokenKind::*;
-    let mut toks_iter = toks.iter().cloned().peekable();
-    let mut l_map = LABEL_MAP.lock().unwrap();
-    let mut loc_counter = start_addr;
-    while let Some((fname, tok, span)) = toks_iter.next() {
-        match tok {
-            Label(name) => {
-                if let Some((file, location, _)) = l_map.get(&name) {
-                    handle_core_error(
-                        &fname,
-                        &span,
-                        error_count,
-                        &format!("label `{}` has already been declared", name.magenta()),
-                        Some(format!("{} previous declaration here", "╮".bright_red())),
-                    );
-                    let (num, data) = highlight_range_in_file(&fname, location);
-                    println!(
-                        "         {}{} in {} {}{} {:^6} {} {}\n",
-                        "╰".bright_red(),
-                        ">".yellow(),
-                        file.green(),
-                        "-".bright_red(),
-                        ">".yellow(),
-                        num.to_string().blue(),
-                        "│".blue(),
-                        data
-                    );
-                } else {
-                    l_map.insert(
-                        name,
-                        (fname.to_string(), span.clone(), loc_counter as usize),
-                    );
+        let rejected = self.process_directives(toks, start_addr);
+        let mut new_toks = Vec::new();
+        {
+            // rejected lines are dropped so codegen doesn't report them again
+            let mut skipping = false;
+            for (index, (fname, tok, span)) in toks.iter().enumerate() {
+                if rejected.contains(&index) {
+                    skipping = true;
                 }
-            }
-            Directive(data) => match data.trim() {
-                "start" => {
-                    if let Some((_, TokenKind::IntLit(_), _)) = toks_iter.peek() {
-                        toks_iter.next();
-                    } else if let Some((_, TokenKind::Mem(_), _)) = toks_iter.peek() {
-                        toks_iter.next();
-                    }
+                if let TokenKind::Newline = tok {
+                    skipping = false;
                 }
-                "pad" => {
-                    if let Some((_, TokenKind::IntLit(v), _)) = toks_iter.peek() {
-                        loc_counter += v;
-                        toks_iter.next();
-                    } else {
-                        handle_core_error(
-                            &fname,
-                            &span,
-                            error_count,
-                            ".pad directive must be succeeded by literal",
-                            None,
-                        );
-                        break;
-                    }
+                if let TokenKind::Label(_) = tok {
+                } else if !skipping {
+                    new_toks.push((fname.to_string(), tok.clone(), span.clone()));
                 }
-                "word" => {
-                    if toks_iter
-                        .peek()
-                        .is_some_and(|v| matches!(v.1, TokenKind::Ident(_) | TokenKind::IntLit(_)))
-                    {
-                        loc_counter += 1;
-                        toks_iter.next();
-                    } else {
-                        handle_core_error(
-                            &fname,
-                            &span,
-                            error_count,
-                            &format!("{} must be succeeded by literal or identifier", data.trim()),
-                            None,
+            }
+        }
+        *toks = new_toks;
+    }
+
+    // returns the index of every token whose line was rejected, after skipping
+    // ahead to the next line to carry on from there. directives that put out
+    // words are replaced by a Placed token holding them
+    fn process_directives(
+        &mut self,
+        toks: &mut [(String, TokenKind, Range<usize>)],
+        start_addr: i64,
+    ) -> Vec<usize> {
+        use crate::TokenKind::*;
+        let mut rejected = Vec::new();
+        let mut placed = Vec::new();
+        // a peeked token is taken from `toks` before it is returned, so once a
+        // token is returned by `next`, exactly the ones up to it have been taken
+        let taken = Cell::new(0);
+        let mut toks_iter = toks
+            .iter()
+            .cloned()
+            .inspect(|_| taken.set(taken.get() + 1))
+            .peekable();
+        let mut loc_counter = start_addr;
+        let mut past_end = false;
+        while let Some((fname, tok, span)) = toks_iter.next() {
+            let index = taken.get() - 1;
+            match tok {
+                Label(name) => {
+                    if let Some((file, location, _)) = self.label_map.get(&name) {
+                        self.diagnostics.push(
+                            Diagnostic::error(
+                                DiagnosticCode::DuplicateLabel,
+                                format!("label `{}` has already been declared", name.magenta()),
+                            )
+                            .at(&fname, span.clone())
+                            .with_label(
+                                file,
+                                location.clone(),
+                                Some(String::from("previous declaration here")),
+                            ),
                         );
-                        break;
-                    }
-                }
-                "asciiz" => {
-                    if let Some((_, TokenKind::StringLit(val), _)) = toks_iter.peek() {
-                        loc_counter += val.len() as i64;
-                        toks_iter.next();
                     } else {
-                        handle_core_error(
-                            &fname,
-                            &span,
-                            error_count,
-                            &format!("{} must be succeeded by string", data.trim()),
-                            None,
+                        self.label_map.insert(
+                            name,
+                            (fname.to_string(), span.clone(), loc_counter as usize),
                         );
-                        break;
                     }
                 }
-                "data" => {
-                    if let Some((_, TokenKind::StringLit(val), _)) = toks_iter.peek() {
-                        let mut glob_str = METADATA_STR.lock().unwrap();
-                        *glob_str = format!("{}{}", glob_str, val);
-                        toks_iter.next();
-                    } else {
+                // where an object goes is up to the linker, so an address in it
+                // would only count from the start of the object
+                Directive(data)
+                    if self.options.output_format == OutputFormat::Object
+                        && matches!(data.trim(), "org" | "align") =>
+                {
+                    self.diagnostics.push(
+                        Diagnostic::error(
+                            DiagnosticCode::InvalidDirective,
+                            format!(".{} cannot be used in an object file", data.trim()),
+                        )
+                        .at(&fname, span.clone())
+                        .with_help(
+                            "its address is not known until it is linked, use .pad for a gap",
+                        ),
+                    );
+                    rejected.push(index);
+                    skip_line(&mut toks_iter);
+                }
+                Directive(data) => match data.trim() {
+                    "start" => {
+                        if let Some((_, TokenKind::IntLit(_), _)) = toks_iter.peek() {
+                            toks_iter.next();
+                        } else if let Some((_, TokenKind::Mem(_), _)) = toks_iter.peek() {
+                            toks_iter.next();
+                        }
+                    }
+                    "pad" | "space" => {
+                        match toks_iter.peek().and_then(|(_, t, _)| self.operand_value(t)) {
+                            Some(count) if count > MEMORY_END - loc_counter => {
+                                self.diagnostics.push(past_the_end(
+                                    &fname,
+                                    &span,
+                                    loc_counter + count,
+                                ));
+                                rejected.push(index);
+                                skip_line(&mut toks_iter);
+                            }
+                            Some(count) if count >= 0 => {
+                                placed.push((
+                                    index,
+                                    DirectiveWords::Fill {
+                                        count: count as usize,
+                                        value: 0,
+                                    },
+                                ));
+                                loc_counter += count;
+                                toks_iter.next();
+                            }
+                            _ => {
+                                handle_core_error(
+                                    &fname,
+                                    &span,
+                                    &mut self.diagnostics,
+                                    DiagnosticCode::InvalidDirective,
+                                    &format!(
+                                        ".{} directive must be succeeded by a word count",
+                                        data.trim()
+                                    ),
+                                    None,
+                                );
+                                rejected.push(index);
+                                skip_line(&mut toks_iter);
+                            }
+                        }
+                    }
+                    "align" => match toks_iter.peek().and_then(|(_, t, _)| self.operand_value(t)) {
+                        Some(size) if size > 0 => {
+                            let gap = (size - loc_counter.rem_euclid(size)) % size;
+                            if gap > MEMORY_END - loc_counter {
+                                self.diagnostics.push(past_the_end(
+                                    &fname,
+                                    &span,
+                                    loc_counter + gap,
+                                ));
+                                rejected.push(index);
+                                skip_line(&mut toks_iter);
+                                continue;
+                            }
+                            placed.push((
+                                index,
+                                DirectiveWords::Fill {
+                                    count: gap as usize,
+                                    value: 0,
+                                },
+                            ));
+                            loc_counter += gap;
+                            toks_iter.next();
+                        }
+                        _ => {
+                            handle_core_error(
+                                &fname,
+                                &span,
+                                &mut self.diagnostics,
+                                DiagnosticCode::InvalidDirective,
+                                ".align directive must be succeeded by a positive word count",
+                                None,
+                            );
+                            rejected.push(index);
+                            skip_line(&mut toks_iter);
+                        }
+                    },
+                    "fill" => {
+                        // .fill count, value
+                        let count = toks_iter.peek().and_then(|(_, t, _)| self.operand_value(t));
+                        let mut value = None;
+                        if count.is_some() {
+                            toks_iter.next();
+                            if toks_iter.next_if(|(_, t, _)| *t == Comma).is_some() {
+                                value =
+                                    toks_iter.peek().and_then(|(_, t, _)| self.operand_value(t));
+                            }
+                        }
+                        match (count, value) {
+                            (Some(count), Some(_)) if count > MEMORY_END - loc_counter => {
+                                self.diagnostics.push(past_the_end(
+                                    &fname,
+                                    &span,
+                                    loc_counter + count,
+                                ));
+                                rejected.push(index);
+                                skip_line(&mut toks_iter);
+                            }
+                            (Some(count), Some(value))
+                                if count >= 0
+                                    && (i16::MIN as i64..=u16::MAX as i64).contains(&value) =>
+                            {
+                                placed.push((
+                                    index,
+                                    DirectiveWords::Fill {
+                                        count: count as usize,
+                                        value: value as i16,
+                                    },
+                                ));
+                                loc_counter += count;
+                                toks_iter.next();
+                            }
+                            (Some(count), Some(value)) if count >= 0 => {
+                                self.diagnostics.push(
+                                    Diagnostic::error(
+                                        DiagnosticCode::InvalidDirective,
+                                        format!(
+                                            "{} cannot fit in a 16-bit word",
+                                            value.to_string().magenta()
+                                        ),
+                                    )
+                                    .at(&fname, span.clone()),
+                                );
+                                rejected.push(index);
+                                skip_line(&mut toks_iter);
+                            }
+                            _ => {
+                                handle_core_error(
+                                    &fname,
+                                    &span,
+                                    &mut self.diagnostics,
+                                    DiagnosticCode::InvalidDirective,
+                                    ".fill directive must be succeeded by a word count and a value",
+                                    None,
+                                );
+                                rejected.push(index);
+                                skip_line(&mut toks_iter);
+                            }
+                        }
+                    }
+                    "org" => {
+                        let target = toks_iter.peek().and_then(|(_, t, _)| self.operand_value(t));
+                        match target {
+                            Some(target) if target >= MEMORY_END => {
+                                self.diagnostics.push(
+                                    Diagnostic::error(
+                                        DiagnosticCode::InvalidDirective,
+                                        format!(".org cannot move to 0x{target:X}, past the last address"),
+                                    )
+                                    .at(&fname, span.clone())
+                                    .with_help(format!("memory ends at 0x{:X}", MEMORY_END - 1)),
+                                );
+                                rejected.push(index);
+                                skip_line(&mut toks_iter);
+                            }
+                            Some(target) if target >= loc_counter => {
+                                placed.push((
+                                    index,
+                                    DirectiveWords::Fill {
+                                        count: (target - loc_counter) as usize,
+                                        value: 0,
+                                    },
+                                ));
+                                loc_counter = target;
+                                toks_iter.next();
+                            }
+                            Some(target) => {
+                                let help = if target < start_addr {
+                                    format!(
+                                        "the program starts at 0x{start_addr:X}, so nothing goes below it"
+                                    )
+                                } else {
+                                    format!(
+                                        "words from 0x{target:X} to 0x{:X} have already been placed",
+                                        loc_counter - 1
+                                    )
+                                };
+                                self.diagnostics.push(
+                                    Diagnostic::error(
+                                        DiagnosticCode::InvalidDirective,
+                                        format!(
+                                            ".org cannot move back to 0x{target:X}, the next word is at 0x{loc_counter:X}"
+                                        ),
+                                    )
+                                    .at(&fname, span.clone())
+                                    .with_help(help),
+                                );
+                                rejected.push(index);
+                                skip_line(&mut toks_iter);
+                            }
+                            None => {
+                                handle_core_error(
+                                    &fname,
+                                    &span,
+                                    &mut self.diagnostics,
+                                    DiagnosticCode::InvalidDirective,
+                                    ".org directive must be succeeded by an address",
+                                    None,
+                                );
+                                rejected.push(index);
+                                skip_line(&mut toks_iter);
+                            }
+                        }
+                    }
+                    "word" => match self.word_values(&fname, &span, &mut toks_iter) {
+                        Ok(values) => {
+                            loc_counter += values.len() as i64;
+                            placed.push((index, DirectiveWords::Values(values)));
+                        }
+                        Err(e) => {
+                            self.diagnostics.push(e);
+                            rejected.push(index);
+                            skip_line(&mut toks_iter);
+                        }
+                    },
+                    "ascii" | "asciiz" | "packed" => {
+                        if let Some((_, TokenKind::StringLit(val), place)) = toks_iter.peek() {
+                            match string_words(data.trim(), val) {
+                                Ok(words) => {
+                                    loc_counter += words.len() as i64;
+                                    toks_iter.next();
+                                }
+                                Err(c) => {
+                                    let bits = if data.trim() == "packed" { 8 } else { 16 };
+                                    self.diagnostics.push(
+                                        Diagnostic::error(
+                                            DiagnosticCode::InvalidDirective,
+                                            format!(
+                                                "character `{}` cannot fit in {bits} bits",
+                                                c.escape_debug().to_string().magenta()
+                                            ),
+                                        )
+                                        .at(&fname, place.clone()),
+                                    );
+                                    rejected.push(index);
+                                    skip_line(&mut toks_iter);
+                                }
+                            }
+                        } else {
+                            handle_core_error(
+                                &fname,
+                                &span,
+                                &mut self.diagnostics,
+                                DiagnosticCode::InvalidDirective,
+                                &format!("{} must be succeeded by string", data.trim()),
+                                None,
+                            );
+                            rejected.push(index);
+                            skip_line(&mut toks_iter);
+                        }
+                    }
+                    "incbin" => {
+                        // .incbin "file"[, offset[, length]]
+                        let path = toks_iter.next_if(|(_, t, _)| matches!(t, StringLit(_)));
+                        let mut numbers = Vec::new();
+                        while path.is_some() {
+                            let Some((_, _, comma)) = toks_iter.next_if(|(_, t, _)| *t == Comma)
+                            else {
+                                break;
+                            };
+                            let operand = toks_iter.next_if(|(_, t, _)| *t != Newline);
+                            let at = operand.as_ref().map_or(comma, |(_, _, at)| at.clone());
+                            let number = operand.and_then(|(_, t, _)| self.operand_value(&t));
+                            numbers.push((number, at));
+                        }
+                        let words = match (path, numbers.as_slice()) {
+                            (Some(_), [_, _, (_, at), ..]) => Err(Diagnostic::error(
+                                DiagnosticCode::InvalidDirective,
+                                String::from("unexpected operand after the .incbin length"),
+                            )
+                            .at(&fname, at.clone())),
+                            (Some((_, StringLit(path), place)), numbers) => {
+                                // the offset, then the length, each a count of bytes
+                                let counts: Result<Vec<usize>, Diagnostic> = numbers
+                                    .iter()
+                                    .zip(["offset", "length"])
+                                    .map(|((number, at), what)| match number {
+                                        Some(n) if *n >= 0 => Ok(*n as usize),
+                                        _ => Err(Diagnostic::error(
+                                            DiagnosticCode::InvalidDirective,
+                                            format!("the .incbin {what} must be a number of bytes"),
+                                        )
+                                        .at(&fname, at.clone())
+                                        .with_help("give a count of 0 or more")),
+                                    })
+                                    .collect();
+                                counts.and_then(|counts| {
+                                    let offset = counts.first().copied().unwrap_or(0);
+                                    self.load_binary(
+                                        &fname,
+                                        &place,
+                                        &path,
+                                        offset,
+                                        counts.get(1).copied(),
+                                    )
+                                })
+                            }
+                            _ => Err(Diagnostic::error(
+                                DiagnosticCode::InvalidDirective,
+                                String::from(".incbin directive must be succeeded by a file name"),
+                            )
+                            .at(&fname, span.clone())
+                            .with_help("an offset and a length in bytes can follow the name")),
+                        };
+                        match words {
+                            Ok(words) => {
+                                loc_counter += words.len() as i64;
+                                placed.push((index, DirectiveWords::Binary(words)));
+                            }
+                            Err(e) => {
+                                self.diagnostics.push(e);
+                                rejected.push(index);
+                                skip_line(&mut toks_iter);
+                            }
+                        }
+                    }
+                    "global" => {
+                        if let Some((_, TokenKind::Ident(name), place)) =
+                            toks_iter.next_if(|(_, t, _)| matches!(t, TokenKind::Ident(_)))
+                        {
+                            let declared = (fname.to_string(), span.start..place.end);
+                            self.globals.entry(name).or_insert(declared);
+                        } else {
+                            handle_core_error(
+                                &fname,
+                                &span,
+                                &mut self.diagnostics,
+                                DiagnosticCode::InvalidDirective,
+                                &format!("{} must be succeeded by a label name", data.trim()),
+                                None,
+                            );
+                            rejected.push(index);
+                            skip_line(&mut toks_iter);
+                        }
+                    }
+                    "data" => {
+                        if let Some((_, TokenKind::StringLit(val), _)) = toks_iter.peek() {
+                            self.metadata_str.push_str(val);
+                            toks_iter.next();
+                        } else {
+                            handle_core_error(
+                                &fname,
+                                &span,
+                                &mut self.diagnostics,
+                                DiagnosticCode::InvalidDirective,
+                                &format!("{} must be succeeded by string", data.trim()),
+                                None,
+                            );
+                            rejected.push(index);
+                            skip_line(&mut toks_iter);
+                        }
+                    }
+                    _ => {
                         handle_core_error(
                             &fname,
                             &span,
-                            error_count,
-                            &format!("{} must be succeeded by string", data.trim()),
+                            &mut self.diagnostics,
+                            DiagnosticCode::InvalidDirective,
+                            &format!("unrecognized directive {data}"),
                             None,
                         );
-                        break;
+                        rejected.push(index);
+                        skip_line(&mut toks_iter);
                     }
-                }
+                },
+                Instruction(_) => loc_counter += 1,
+                Newline | LeftBrace | RightBrace => (),
                 _ => {
                     handle_core_error(
                         &fname,
                         &span,
-                        error_count,
-                        &format!("unrecognized directive {data}"),
+                        &mut self.diagnostics,
+                        DiagnosticCode::UnexpectedToken,
+                        &format!("unrecognized {tok}"),
                         None,
                     );
-                    break;
+                    rejected.push(index);
+                    skip_line(&mut toks_iter);
                 }
-            },
-            Instruction(_) => loc_counter += 1,
-            Newline | LeftBrace | RightBrace => (),
-            _ => {
-                handle_core_error(
-                    &fname,
-                    &span,
-                    error_count,
-                    &format!("unrecognized {tok}"),
-                    None,
-                );
-                break;
+            }
+            // only the first line that doesn't fit is reported
+            if loc_counter > MEMORY_END && !past_end {
+                past_end = true;
+                self.diagnostics
+                    .push(past_the_end(&fname, &span, loc_counter));
+            }
+        }
+        for (index, words) in placed {
+            if let (_, Directive(name), _) = &toks[index] {
+                toks[index].1 = Placed(PlacedDirective {
+                    name: name.to_string(),
+                    words,
+                });
             }
         }
+        let mut undefined: Vec<_> = self
+            .globals
+            .iter()
+            .filter(|(name, _)| !self.label_map.contains_key(*name))
+            .collect();
+        undefined.sort_by_key(|(_, (file, place))| (file.to_string(), place.start));
+        for (name, (file, place)) in undefined {
+            let (info, similars) = self.find_similar_entries(name);
+            self.diagnostics.push(
+                Diagnostic::error(
+                    DiagnosticCode::UnknownLabel,
+                    format!("cannot export \"{}\", no such label", name.magenta()),
+                )
+                .at(file, place.clone())
+                .with_help_opt(info)
+                .with_labels(similars),
+            );
+        }
+        rejected
+    }
+
+    // reads the comma-separated values of a .word, each a sum of literals and
+    // constants with at most one label, as in `.word table + 2, (SIZE * 3)`
+    fn word_values<I: Iterator<Item = (String, TokenKind, Range<usize>)>>(
+        &mut self,
+        fname: &str,
+        directive: &Range<usize>,
+        toks_iter: &mut Peekable<I>,
+    ) -> Result<Vec<WordValue>, Diagnostic> {
+        use crate::TokenKind::*;
+        let mut values = Vec::new();
+        loop {
+            let mut value: Option<WordValue> = None;
+            let mut sign = 1;
+            let mut wants_term = true;
+            while let Some((_, tok, place)) =
+                toks_iter.next_if(|(_, t, _)| !matches!(t, Newline | Comma))
+            {
+                let value = value.get_or_insert(WordValue {
+                    label: None,
+                    addend: 0,
+                    place: place.clone(),
+                });
+                value.place.end = place.end;
+                match tok {
+                    Plus | Minus if !wants_term => {
+                        sign = if tok == Minus { -1 } else { 1 };
+                        wants_term = true;
+                        continue;
+                    }
+                    Minus if wants_term && sign > 0 => {
+                        sign = -1;
+                        continue;
+                    }
+                    // `label-2` is lexed as the label and then the literal -2
+                    IntLit(n) if wants_term || n < 0 => value.addend += sign * n,
+                    Ident(name) if wants_term => {
+                        if let Some((_, _, v)) = self.v_map.get(&name) {
+                            value.addend += sign * v;
+                            self.uses.constants.insert(name);
+                        } else if sign < 0 {
+                            return Err(Diagnostic::error(
+                                DiagnosticCode::InvalidDirective,
+                                format!("the address of `{}` cannot be subtracted", name.magenta()),
+                            )
+                            .at(fname, place));
+                        } else if let Some((other, _)) = &value.label {
+                            return Err(Diagnostic::error(
+                                DiagnosticCode::InvalidDirective,
+                                format!(
+                                    "cannot add the addresses of `{}` and `{}`",
+                                    other.magenta(),
+                                    name.magenta()
+                                ),
+                            )
+                            .at(fname, place)
+                            .with_help("a .word value can refer to one label at most"));
+                        } else {
+                            value.label = Some((name, place));
+                        }
+                    }
+                    _ => {
+                        return Err(Diagnostic::error(
+                            DiagnosticCode::InvalidDirective,
+                            format!("unexpected {tok} in .word value"),
+                        )
+                        .at(fname, place)
+                        .with_help("values are literals, constants and labels joined by + and -"));
+                    }
+                }
+                sign = 1;
+                wants_term = false;
+            }
+            match value {
+                Some(value) if !wants_term => values.push(value),
+                Some(value) => {
+                    return Err(Diagnostic::error(
+                        DiagnosticCode::InvalidDirective,
+                        String::from("expected a value after this operator"),
+                    )
+                    .at(fname, value.place))
+                }
+                None => {
+                    return Err(Diagnostic::error(
+                        DiagnosticCode::InvalidDirective,
+                        String::from(".word directive must be succeeded by a value"),
+                    )
+                    .at(fname, directive.clone())
+                    .with_help("separate several values with commas"))
+                }
+            }
+            if toks_iter.next_if(|(_, t, _)| *t == Comma).is_none() {
+                return Ok(values);
+            }
+        }
+    }
+
+    // the value of a directive operand: a literal, `[addr]` or a constant, with
+    // parenthesized expressions already folded into literals by the first pass
+    pub fn operand_value(&mut self, tok: &TokenKind) -> Option<i64> {
+        match tok {
+            TokenKind::IntLit(v) => Some(*v),
+            TokenKind::Mem(addr) => {
+                let (inner, _) = addr.data.first()?;
+                self.operand_value(&inner.clone())
+            }
+            TokenKind::Ident(name) => {
+                let (_, _, v) = self.v_map.get(name)?;
+                self.uses.constants.insert(name.to_string());
+                Some(*v)
+            }
+            _ => None,
+        }
     }
 }
+
+// an error for a line that would place words up to, but not including, `end`
+fn past_the_end(fname: &str, place: &Range<usize>, end: i64) -> Diagnostic {
+    Diagnostic::error(
+        DiagnosticCode::InvalidDirective,
+        format!(
+            "words would be placed up to 0x{:X}, past the last address",
+            end - 1
+        ),
+    )
+    .at(fname, place.clone())
+    .with_help(format!("memory ends at 0x{:X}", MEMORY_END - 1))
+}
+
+fn skip_line<I: Iterator<Item = (String, TokenKind, Range<usize>)>>(toks_iter: &mut Peekable<I>) {
+    while toks_iter
+        .next_if(|(_, t, _)| *t != TokenKind::Newline)
+        .is_some()
+    {}
+}
@@ -3,96 +3,190 @@ use colored::*;
 use std::fs::File;
 use std::io::Read;
 use std::ops::Range;
+use std::path::Path;
 
-pub fn process_includes(toks: &mut Vec<(String, TokenKind, Range<usize>)>, error_count: &mut i32) {
-    loop {
-        let mut included_toks = Vec::new();
-        let mut has_include = false;
-
-        use crate::TokenKind::*;
-        for (index, (fname, element, loc)) in toks.iter().enumerate() {
-            if let IncludeFile(file_path) = element {
-                has_include = true;
-                if *file_path == *fname {
-                    handle_core_error(
-                        fname,
-                        loc,
-                        error_count,
-                        &format!("cannot include {fname} in itself"),
-                        None,
-                    );
-                    println!("{toks:#?}");
-                    break;
-                }
-                let mut file_data = match File::open(file_path) {
-                    Ok(file) => file,
-                    Err(e) => {
-                        handle_core_error(
-                            fname,
-                            loc,
-                            error_count,
-                            &format!("cannot read file \"{}\": {e}", file_path.bold().magenta()),
-                            None,
-                        );
-                        std::process::exit(1);
-                    }
-                };
+impl Assembler {
+    /// Finds the file an `@include` in `from` refers to
+    ///
+    /// The path is tried as written, then relative to the including file, then
+    /// relative to each of the configured include paths. If none of them exist
+    /// the path is returned as written so the error names what the user wrote.
+    pub fn resolve_include(&self, from: &str, path: &str) -> String {
+        let requested = Path::new(path);
+        if requested.is_absolute() || requested.exists() {
+            return path.to_string();
+        }
+        let including_dir = Path::new(from).parent().map(Path::to_path_buf);
+        including_dir
+            .into_iter()
+            .chain(self.options.include_paths.iter().cloned())
+            .map(|dir| dir.join(requested))
+            .find(|candidate| candidate.exists())
+            .map(|found| found.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string())
+    }
 
-                let mut contents = String::new();
-                match file_data.read_to_string(&mut contents) {
-                    Ok(_) => (),
-                    Err(e) => {
-                        handle_core_error(
-                            fname,
-                            loc,
-                            error_count,
-                            &format!("cannot read file \"{}\": {e}", file_path.bold().magenta()),
-                            None,
-                        );
-                    }
-                }
-                if let Some(mut parser) = create_parser(file_path, &contents, error_count) {
-                    if let Some(tokens) = parse_tokens(&mut parser, &contents, error_count) {
-                        for token in tokens.into_iter().rev() {
-                            included_toks.insert(index, token);
-                        }
-                    }
-                }
-            } else {
-                included_toks.push((fname.to_string(), element.clone(), loc.clone()));
-            }
+    /// Reads the words an `.incbin` of `path` in `from` embeds
+    ///
+    /// The file is found the same way as an `@include`. `length` bytes are
+    /// taken from `offset`, or the rest of the file when there is no length,
+    /// and put two to a word with the first byte high.
+    pub fn load_binary(
+        &self,
+        from: &str,
+        place: &Range<usize>,
+        path: &str,
+        offset: usize,
+        length: Option<usize>,
+    ) -> Result<Vec<i16>, Diagnostic> {
+        let file_path = self.resolve_include(from, path);
+        let contents = std::fs::read(&file_path).map_err(|e| {
+            Diagnostic::error(
+                DiagnosticCode::IncludeError,
+                format!("cannot read file \"{}\": {e}", file_path.bold().magenta()),
+            )
+            .at(from, place.clone())
+        })?;
+        let end = length.map_or(contents.len().max(offset), |length| offset + length);
+        let Some(bytes) = contents.get(offset..end) else {
+            return Err(Diagnostic::error(
+                DiagnosticCode::InvalidDirective,
+                format!(
+                    "cannot take bytes {offset} to {end} of \"{}\", it is {} bytes long",
+                    file_path.bold().magenta(),
+                    contents.len()
+                ),
+            )
+            .at(from, place.clone()));
+        };
+        if bytes.len() % 2 == 1 && self.options.incbin_padding == IncbinPadding::Error {
+            return Err(Diagnostic::error(
+                DiagnosticCode::InvalidDirective,
+                format!(
+                    "cannot embed an odd number of bytes from \"{}\"",
+                    file_path.bold().magenta()
+                ),
+            )
+            .at(from, place.clone())
+            .with_help("give an even length, or pass `--incbin-padding zero`"));
         }
+        Ok(bytes
+            .chunks(2)
+            .map(|pair| i16::from_be_bytes([pair[0], pair.get(1).copied().unwrap_or(0)]))
+            .collect())
+    }
 
-        *toks = included_toks;
-        if !has_include {
-            break;
+    /// Reads and parses the file an `@include` at `loc` in `fname` names
+    ///
+    /// The first pass calls this when it reaches the `@include` line, so the
+    /// file sees the defines and constants declared above that line, and the
+    /// lines after it see what the file declares.
+    pub fn include_file(
+        &mut self,
+        fname: &str,
+        loc: &Range<usize>,
+        path: &str,
+    ) -> Result<Vec<(String, TokenKind, Range<usize>)>, Diagnostic> {
+        let file_path = &self.resolve_include(fname, path);
+        if let Some(cycle) = include_cycle(&self.including, fname, loc, file_path) {
+            return Err(cycle);
         }
+        let contents = std::fs::read_to_string(file_path).map_err(|e| {
+            Diagnostic::error(
+                DiagnosticCode::IncludeError,
+                format!("cannot read file \"{}\": {e}", file_path.bold().magenta()),
+            )
+            .at(fname, loc.clone())
+        })?;
+        self.includes
+            .entry(file_path.to_string())
+            .or_insert_with(|| (fname.to_string(), loc.clone()));
+        self.including.push((fname.to_string(), loc.clone()));
+        let mut parser = create_parser(self, file_path, &contents);
+        let parsed = parse_tokens(self, &mut parser);
+        self.including.pop();
+        Ok(parsed)
+    }
+
+    /// Puts the tokens of every `@include`d file in place of its `@include`
+    pub fn process_includes(&mut self, toks: &mut Vec<(String, TokenKind, Range<usize>)>) {
+        *toks = std::mem::take(toks)
+            .into_iter()
+            .flat_map(|(fname, element, loc)| match element {
+                TokenKind::Included(mut included) => {
+                    self.process_includes(&mut included);
+                    included
+                }
+                element => vec![(fname, element, loc)],
+            })
+            .collect();
     }
 }
 
-pub fn read_file(file_path: &str) -> String {
+// an error if `file_path` is one of the files being read where `fname`
+// includes it, which would include it in itself forever
+fn include_cycle(
+    chain: &[(String, Range<usize>)],
+    fname: &str,
+    loc: &Range<usize>,
+    file_path: &str,
+) -> Option<Diagnostic> {
+    let open: Vec<&str> = chain
+        .iter()
+        .map(|(f, _)| f.as_str())
+        .chain(std::iter::once(fname))
+        .collect();
+    let start = open.iter().position(|f| same_file(f, file_path))?;
+    let mut error = Diagnostic::error(
+        DiagnosticCode::IncludeCycle,
+        format!(
+            "cannot include \"{}\" in itself",
+            open[start].bold().magenta()
+        ),
+    )
+    .at(fname, loc.clone());
+    for (index, (from, place)) in chain.iter().enumerate().skip(start) {
+        error = error.with_label(
+            from,
+            place.clone(),
+            Some(format!("includes \"{}\"", open[index + 1])),
+        );
+    }
+    Some(error)
+}
+
+// whether two paths name the same file, however they were written
+fn same_file(a: &str, b: &str) -> bool {
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+// reads a source file, reporting failures as a diagnostic
+pub fn load_file(file_path: &str) -> Result<String, Diagnostic> {
     let mut file_data = match File::open(file_path) {
         Ok(file) => file,
         Err(e) => {
-            println!(
-                "{}: cannot open file \"{}\": {e}",
-                "error".bright_red(),
-                file_path.bold().magenta()
-            );
-            std::process::exit(1)
+            return Err(Diagnostic::error(
+                DiagnosticCode::Io,
+                format!("cannot open file \"{}\": {e}", file_path.bold().magenta()),
+            ));
         }
     };
 
     let mut contents = String::new();
     match file_data.read_to_string(&mut contents) {
-        Ok(_) => contents,
-        Err(e) => {
-            println!(
-                "{}: cannot read file \"{}\": {e}",
-                "error".bright_red(),
-                file_path.bold().magenta()
-            );
-            std::process::exit(1);
-        }
+        Ok(_) => Ok(contents),
+        Err(e) => Err(Diagnostic::error(
+            DiagnosticCode::Io,
+            format!("cannot read file \"{}\": {e}", file_path.bold().magenta()),
+        )),
     }
 }
+
+// re-reads a source file to display it alongside a diagnostic. an unreadable
+// file yields an empty string, as the error itself is still worth showing
+pub fn read_file(file_path: &str) -> String {
+    load_file(file_path).unwrap_or_default()
+}
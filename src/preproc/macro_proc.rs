@@ -1,158 +1,203 @@
 use crate::*;
 use colored::*;
-use std::collections::HashMap;
 use std::ops::Range;
 
-pub fn process_macros(toks: &mut Vec<(String, TokenKind, Range<usize>)>, error_count: &mut i32) {
-    let mut mac_locs = Vec::new();
-    for (index, (fname, element, span)) in toks.iter().enumerate() {
-        if let Macro(data) = element {
-            let mut mac_map = MACRO_MAP.lock().unwrap();
-            if let Some((_, found_data)) = mac_map.get(&data.name.0) {
-                handle_core_error(
-                    fname,
-                    span,
-                    error_count,
-                    &format!("macro `{}` was declared twice", found_data.name.0.magenta()),
-                    Some(format!("{} previous declaration here", "╮".bright_red())),
-                );
-                let (num, data) = highlight_range_in_file(
-                    &found_data.file,
-                    &(found_data.name.1.start..found_data.name.1.end),
-                );
-                println!(
-                    "         {}{} in {} {}{} {:^6} {} {}\n",
-                    "╰".bright_red(),
-                    ">".yellow(),
-                    found_data.file.green(),
-                    "-".bright_red(),
-                    ">".yellow(),
-                    num.to_string().blue(),
-                    "│".blue(),
-                    data
+// how many macro calls deep an expansion can go
+const MAX_MACRO_DEPTH: usize = 64;
+// how many macro calls one program can expand - a program only has room for
+// 65536 words, so going past this means the calls are fanning out without end
+const MAX_MACRO_EXPANSIONS: usize = 1 << 16;
+
+impl Assembler {
+    pub fn process_macros(&mut self, toks: &mut Vec<(String, TokenKind, Range<usize>)>) {
+        use crate::TokenKind::*;
+
+        let mut mac_locs = Vec::new();
+        for (index, (fname, element, span)) in toks.iter().enumerate() {
+            if let Macro(data) = element {
+                if let Some((_, found_data)) = self.macro_map.get(&data.name.0) {
+                    self.diagnostics.push(
+                        Diagnostic::error(
+                            DiagnosticCode::DuplicateMacro,
+                            format!("macro `{}` was declared twice", found_data.name.0.magenta()),
+                        )
+                        .at(fname, span.clone())
+                        .with_label(
+                            &found_data.file,
+                            found_data.name.1.clone(),
+                            Some(String::from("previous declaration here")),
+                        ),
+                    );
+                }
+                self.macro_map.insert(
+                    data.name.0.to_string(),
+                    (data.file.to_string(), data.clone()),
                 );
+                mac_locs.push(index);
             }
-            mac_map.insert(
-                data.name.0.to_string(),
-                (data.file.to_string(), data.clone()),
-            );
-            mac_locs.push(index);
         }
-    }
 
-    for element in mac_locs.iter().rev() {
-        toks.remove(*element);
+        for element in mac_locs.iter().rev() {
+            toks.remove(*element);
+        }
+
+        // the calls each token was expanded from, outermost first
+        let mut chains: Vec<Vec<ExpansionSite>> = toks
+            .iter()
+            .map(|(_, element, _)| match element {
+                Instruction(ins) => ins.expansion.clone(),
+                _ => Vec::new(),
+            })
+            .collect();
+        // a macro body can call other macros, which are expanded in the next round
+        let mut expansions = 0;
+        while self.expand_macro_calls(toks, &mut chains, &mut expansions) {}
     }
 
-    let mut mac_call_data = Vec::new();
-    let mut in_call = false;
-    let mut curr_mac = None;
+    // expands every macro call in `toks` once, returning whether there were any
+    fn expand_macro_calls(
+        &mut self,
+        toks: &mut Vec<(String, TokenKind, Range<usize>)>,
+        chains: &mut Vec<Vec<ExpansionSite>>,
+        expansions: &mut usize,
+    ) -> bool {
+        use crate::TokenKind::*;
 
-    let mut expanded_loc_map: HashMap<usize, Vec<(String, TokenKind, Range<usize>)>> =
-        HashMap::new();
-    let mut expanded_indices = Vec::new();
+        let mut found = false;
+        let mut new_tokens = Vec::new();
+        let mut new_chains = Vec::new();
+        let mut tokerator = toks.drain(..).zip(chains.drain(..));
+        while let Some(((fname, element, span), chain)) = tokerator.next() {
+            let MacroCall(call) = element else {
+                new_tokens.push((fname, element, span));
+                new_chains.push(chain);
+                continue;
+            };
+            found = true;
+            let mut mac_call_data = vec![(MacroCall(call.to_string()), span.clone())];
+            let mut end = span.clone();
+            for ((_, val, place), _) in tokerator.by_ref() {
+                if val == RightParen {
+                    end = place;
+                    break;
+                }
+                mac_call_data.push((val, place));
+            }
 
-    let mac_map = MACRO_MAP.lock().unwrap();
-    let mut counter = 0;
-    for (fname, element, span) in toks.iter() {
-        counter += 1;
-        if let MacroCall(call) = element {
-            in_call = true;
-            mac_call_data = Vec::new();
-            mac_call_data.push((MacroCall(call.to_string()), span.clone()));
-            if let Some(v) = mac_map.get(call) {
-                curr_mac = Some(v);
-            } else {
-                std::mem::drop(mac_map);
-                let similars = find_similar_entries(call);
-                let info = if let (Some(s), _) = similars {
-                    Some(format!("{} {s}", "╮".bright_red()))
-                } else {
-                    None
-                };
-                handle_core_error(
-                    fname,
-                    span,
-                    error_count,
-                    &format!("cannot find macro \"{}\"", call.magenta()),
-                    info,
-                );
-                let size = similars.1.len() - 1;
-                let max_filename_length = similars
-                    .1
-                    .iter()
-                    .map(|(filename, _)| filename.len())
-                    .max()
-                    .unwrap_or(0);
-                for (index, (filename, location)) in similars.1.into_iter().enumerate() {
-                    let (l_num, data) = highlight_range_in_file(&filename, &location);
-                    let connector = if index != size { "├" } else { "╰" };
-                    println!(
-                        "         {}{} in {:<width$} {}{} {:^6} {} {}",
-                        connector.bright_red(),
-                        ">".yellow(),
-                        filename.green(),
-                        "-".bright_red(),
-                        ">".yellow(),
-                        l_num.to_string().blue(),
-                        "│".blue(),
-                        data,
-                        width = max_filename_length,
-                    );
+            // nothing decides when a recursive call would stop, so it never would
+            if chain.iter().any(|site| site.name == call) {
+                self.diagnostics.push(with_backtrace(
+                    Diagnostic::error(
+                        DiagnosticCode::MacroSyntax,
+                        format!("macro `{}` calls itself", call.magenta()),
+                    )
+                    .at(&fname, span.clone())
+                    .with_help(format!(
+                        "`{}` is called from its own expansion",
+                        call.magenta()
+                    )),
+                    &chain,
+                ));
+                continue;
+            }
+            if chain.len() >= MAX_MACRO_DEPTH {
+                self.diagnostics.push(with_backtrace(
+                    Diagnostic::error(
+                        DiagnosticCode::MacroSyntax,
+                        format!("macro `{}` is nested too deeply", call.magenta()),
+                    )
+                    .at(&fname, span.clone())
+                    .with_help(format!(
+                        "macros can call each other at most {MAX_MACRO_DEPTH} deep"
+                    )),
+                    &chain,
+                ));
+                continue;
+            }
+            *expansions += 1;
+            if *expansions > MAX_MACRO_EXPANSIONS {
+                // only the first call past the limit is reported
+                if *expansions == MAX_MACRO_EXPANSIONS + 1 {
+                    self.diagnostics.push(with_backtrace(
+                        Diagnostic::error(
+                            DiagnosticCode::MacroSyntax,
+                            format!("too many macro calls to expand `{}`", call.magenta()),
+                        )
+                        .at(&fname, span.clone())
+                        .with_help(format!(
+                            "a program can expand at most {MAX_MACRO_EXPANSIONS} macro calls"
+                        )),
+                        &chain,
+                    ));
                 }
-                println!();
-                break;
+                continue;
             }
-            continue;
-        }
-        if let RightParen = element {
-            in_call = false;
-            if let Some((_, m)) = curr_mac {
-                match m.expand(span, fname, &read_file(fname), &mac_call_data) {
-                    Ok(v) => {
-                        expanded_loc_map.insert(counter, v.clone());
-                        expanded_indices.push(counter);
-                    }
-                    Err(errors) => {
-                        for e in errors {
-                            println!("{e}");
-                            *error_count += 1;
+            let Some((_, m)) = self.macro_map.get(&call) else {
+                let (info, similars) = self.find_similar_entries(&call);
+                self.diagnostics.push(with_backtrace(
+                    Diagnostic::error(
+                        DiagnosticCode::UnknownMacro,
+                        format!("cannot find macro \"{}\"", call.magenta()),
+                    )
+                    .at(&fname, span.clone())
+                    .with_help_opt(info)
+                    .with_labels(similars),
+                    &chain,
+                ));
+                continue;
+            };
+            self.uses.macros.insert(call.to_string());
+            let expanded = m.expand(&end, &fname, &mac_call_data);
+            let mut inner = chain;
+            inner.push(ExpansionSite {
+                name: call,
+                file: fname,
+                span,
+            });
+            match expanded {
+                Ok(expanded) => {
+                    for (f, mut element, s) in expanded {
+                        if let Instruction(ins) = &mut element {
+                            ins.expansion = inner.clone();
                         }
+                        new_tokens.push((f, element, s));
+                        new_chains.push(inner.clone());
                     }
                 }
-            }
-            continue;
-        }
-        if in_call {
-            mac_call_data.push((element.clone(), span.clone()));
-        }
-    }
-
-    let size = toks.len();
-    for i in 0..size {
-        if expanded_indices.contains(&i) {
-            let expanded = expanded_loc_map.get(&i).unwrap();
-            for element in expanded.iter().rev() {
-                let (z, x, y) = element;
-                toks.insert(i, (z.to_string(), x.clone(), y.clone()));
+                // the call that failed to expand is the innermost one
+                Err(errors) => self.diagnostics.extend(
+                    errors
+                        .into_iter()
+                        .map(|error| with_backtrace(error, &inner)),
+                ),
             }
         }
+        drop(tokerator);
+        *toks = new_tokens;
+        *chains = new_chains;
+        found
     }
-    use crate::TokenKind::*;
+}
 
-    let mut new_tokens = Vec::new();
-    let mut tokerator = toks.clone().into_iter();
-    while let Some((f, v, s)) = tokerator.next() {
-        match v {
-            MacroCall(_) => {
-                for (_, val, _) in tokerator.by_ref() {
-                    if val == RightParen {
-                        break;
-                    }
-                }
-            }
-            _ => new_tokens.push((f, v, s)),
+/// Points `error` at the macro calls and repeated blocks it was expanded
+/// from, outermost first
+pub fn with_backtrace(mut error: Diagnostic, chain: &[ExpansionSite]) -> Diagnostic {
+    let mut shown: Vec<&ExpansionSite> = Vec::new();
+    for site in chain {
+        // a macro that calls itself would repeat the same call every level
+        if shown
+            .iter()
+            .any(|s| s.file == site.file && s.span == site.span)
+        {
+            continue;
         }
+        shown.push(site);
+        let label = if site.name.starts_with('.') {
+            format!("repeated by this {}", site.name)
+        } else {
+            format!("in expansion of this call of `{}`", site.name)
+        };
+        error = error.with_label(&site.file, site.span.clone(), Some(label));
     }
-    *toks = new_tokens;
+    error
 }
@@ -1,6 +1,6 @@
 pub mod directive_proc;
 pub mod include_proc;
 pub mod macro_proc;
-pub use directive_proc::*;
+pub mod repeat_proc;
 pub use include_proc::*;
 pub use macro_proc::*;
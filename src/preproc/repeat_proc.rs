@@ -0,0 +1,274 @@
+use crate::*;
+use std::ops::Range;
+
+type Token = (String, TokenKind, Range<usize>);
+
+// how many words the copies of every block can place between them - any more
+// could not fit in the 16-bit address space
+const MAX_REPEATED_WORDS: usize = 1 << 16;
+
+impl Assembler {
+    /// Replaces every `.rept` and `.irp` block with copies of its body
+    ///
+    /// The outermost block is expanded first and the copies are looked at
+    /// again, so nested blocks are expanded once per copy of the block around
+    /// them. Every copy keeps the spans of the body it came from, and its
+    /// instructions record the block they were repeated by.
+    pub fn process_repeats(&mut self, toks: &mut Vec<Token>) {
+        let mut placed = 0;
+        while let Some(start) = toks.iter().position(|(_, t, _)| is_repeat(t)) {
+            let header_end = line_end(toks, start);
+            let Some(end) = matching_endr(toks, header_end) else {
+                let (fname, TokenKind::Directive(kind), place) = &toks[start] else {
+                    unreachable!()
+                };
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        DiagnosticCode::InvalidDirective,
+                        format!("this .{kind} has no .endr"),
+                    )
+                    .at(fname, place.clone())
+                    .with_help("close it with .endr"),
+                );
+                toks.drain(start..header_end);
+                continue;
+            };
+            let body = toks[(header_end + 1).min(end)..end].to_vec();
+            let copies = self.repeat_copies(&toks[start..header_end], &body, &mut placed);
+            toks.splice(start..line_end(toks, end), copies);
+        }
+
+        // a block's .endr has been taken out with it, so these have none
+        let mut kept = Vec::new();
+        for (fname, tok, place) in toks.drain(..) {
+            if tok == TokenKind::Directive(String::from("endr")) {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        DiagnosticCode::InvalidDirective,
+                        String::from(".endr without a matching .rept or .irp"),
+                    )
+                    .at(&fname, place),
+                );
+                continue;
+            }
+            kept.push((fname, tok, place));
+        }
+        *toks = kept;
+    }
+
+    // the copies of `body` a .rept or .irp line asks for, counting the words
+    // they place towards `placed`
+    fn repeat_copies(
+        &mut self,
+        header: &[Token],
+        body: &[Token],
+        placed: &mut usize,
+    ) -> Vec<Token> {
+        let (fname, TokenKind::Directive(kind), place) = &header[0] else {
+            return Vec::new();
+        };
+        let site = ExpansionSite {
+            name: format!(".{kind}"),
+            file: fname.to_string(),
+            span: place.clone(),
+        };
+        let error = |message: String| {
+            Diagnostic::error(DiagnosticCode::InvalidDirective, message).at(fname, place.clone())
+        };
+
+        if kind == "rept" {
+            // the first pass has evaluated the count, or reported why it couldn't
+            let Some([(_, TokenKind::IntLit(count), _)]) = header.get(1..) else {
+                return Vec::new();
+            };
+            let count = *count;
+            if count < 0 {
+                self.diagnostics
+                    .push(error(format!("a block cannot be repeated {count} times")));
+                return Vec::new();
+            }
+            if !self.can_place(&header[0], count as usize, body, placed) {
+                return Vec::new();
+            }
+            return (0..count)
+                .flat_map(|_| body.iter().map(|tok| repeated(tok, &site, None)))
+                .collect();
+        }
+
+        // .irp name, value, value, ...
+        let name = match header.get(1) {
+            Some((_, TokenKind::Ident(name) | TokenKind::MacroIdent(name), _)) => name,
+            _ => {
+                self.diagnostics.push(
+                    error(String::from(".irp directive must be succeeded by a name"))
+                        .with_help("the body refers to it as `%name`"),
+                );
+                return Vec::new();
+            }
+        };
+        let mut values = Vec::new();
+        for (index, (_, tok, at)) in header.iter().enumerate().skip(2) {
+            let valid = if index % 2 == 0 {
+                *tok == TokenKind::Comma
+            } else {
+                matches!(
+                    tok,
+                    TokenKind::Register(_)
+                        | TokenKind::IReg(_)
+                        | TokenKind::IntLit(_)
+                        | TokenKind::Ident(_)
+                        | TokenKind::Mem(_)
+                )
+            };
+            if !valid {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        DiagnosticCode::InvalidDirective,
+                        format!("unexpected {tok} in .irp values"),
+                    )
+                    .at(fname, at.clone())
+                    .with_help(
+                        "values are registers, literals, labels or addresses, separated by commas",
+                    ),
+                );
+                return Vec::new();
+            }
+            if index % 2 == 1 {
+                values.push(tok);
+            }
+        }
+
+        if !self.can_place(&header[0], values.len(), body, placed) {
+            return Vec::new();
+        }
+        let mut copies = Vec::new();
+        for value in values {
+            for tok in body {
+                let copy = repeated(tok, &site, Some((name, value)));
+                if let (_, TokenKind::Instruction(ins), at) = &copy {
+                    if let Err((span, message, help)) = ins.is_valid() {
+                        self.diagnostics.push(
+                            Diagnostic::error(DiagnosticCode::InvalidInstruction, message)
+                                .at(&copy.0, span.unwrap_or_else(|| at.clone()))
+                                .with_help_opt(help)
+                                .with_label(
+                                    fname,
+                                    place.clone(),
+                                    Some(format!("with %{name} = {value}")),
+                                ),
+                        );
+                        continue;
+                    }
+                }
+                copies.push(copy);
+            }
+        }
+        copies
+    }
+
+    // whether `copies` copies of `body` still fit in what repetition can place,
+    // reporting the first block that doesn't
+    fn can_place(
+        &mut self,
+        header: &Token,
+        copies: usize,
+        body: &[Token],
+        placed: &mut usize,
+    ) -> bool {
+        let (fname, TokenKind::Directive(kind), place) = header else {
+            return false;
+        };
+        let before = *placed;
+        // every copy is counted as a word at least, so an empty body can't be
+        // copied without end either
+        *placed = placed.saturating_add(copies.saturating_mul(words_placed(body).max(1)));
+        if *placed <= MAX_REPEATED_WORDS {
+            return true;
+        }
+        if before <= MAX_REPEATED_WORDS {
+            self.diagnostics.push(
+                Diagnostic::error(
+                    DiagnosticCode::InvalidDirective,
+                    format!("too many copies to make of this .{kind}"),
+                )
+                .at(fname, place.clone())
+                .with_help(format!(
+                    "repeated blocks can place at most {MAX_REPEATED_WORDS} words, \
+                     the size of the address space"
+                )),
+            );
+        }
+        false
+    }
+}
+
+// the fewest words one copy of `body` places, leaving out blocks nested in it,
+// which are counted when they are repeated in turn
+fn words_placed(body: &[Token]) -> usize {
+    let mut depth = 0;
+    let mut words = 0;
+    for (_, tok, _) in body {
+        match tok {
+            _ if is_repeat(tok) => depth += 1,
+            TokenKind::Directive(d) if d == "endr" => depth -= 1,
+            TokenKind::Instruction(_) | TokenKind::Directive(_) if depth == 0 => words += 1,
+            _ => {}
+        }
+    }
+    words
+}
+
+fn is_repeat(tok: &TokenKind) -> bool {
+    matches!(tok, TokenKind::Directive(d) if d == "rept" || d == "irp")
+}
+
+// the index of the newline ending the line `index` is on, or the end
+fn line_end(toks: &[Token], index: usize) -> usize {
+    toks[index..]
+        .iter()
+        .position(|(_, t, _)| *t == TokenKind::Newline)
+        .map_or(toks.len(), |offset| index + offset)
+}
+
+// the index of the .endr closing a block whose body starts at `from`
+fn matching_endr(toks: &[Token], from: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (index, (_, tok, _)) in toks.iter().enumerate().skip(from) {
+        if is_repeat(tok) {
+            depth += 1;
+        } else if *tok == TokenKind::Directive(String::from("endr")) {
+            if depth == 0 {
+                return Some(index);
+            }
+            depth -= 1;
+        }
+    }
+    None
+}
+
+// a token of a repeated body, with `%name` replaced for .irp
+fn repeated(tok: &Token, site: &ExpansionSite, with: Option<(&String, &TokenKind)>) -> Token {
+    let (fname, tok, place) = tok;
+    let substitute = |name: &String| match with {
+        Some((param, value)) if param == name => Some(value.clone()),
+        _ => None,
+    };
+    let tok = match tok {
+        TokenKind::MacroIdent(name) => substitute(name).unwrap_or_else(|| tok.clone()),
+        TokenKind::Instruction(ins) => {
+            let mut ins = ins.clone();
+            for (arg, _) in &mut ins.operands {
+                if let InstructionArgument::MacroIdent(name) = arg {
+                    if let Some(value) = substitute(name) {
+                        *arg = value.to_tok_kind();
+                    }
+                }
+            }
+            ins.expanded = true;
+            ins.expansion.push(site.clone());
+            TokenKind::Instruction(ins)
+        }
+        _ => tok.clone(),
+    };
+    (fname.to_string(), tok, place.clone())
+}
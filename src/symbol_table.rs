@@ -1,117 +1,133 @@
 use crate::misc::*;
 use crate::*;
 use colored::*;
-use once_cell::sync::Lazy;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops::Range;
-use std::sync::Mutex;
 
-//                                 name    data
-type SymbolTable<T> = Lazy<Mutex<HashMap<String, T>>>;
-// file      place     value
-pub static V_MAP: SymbolTable<(String, Range<usize>, i64)> =
-    Lazy::new(|| Mutex::new(HashMap::new()));
-// file    place        value
-pub static LABEL_MAP: SymbolTable<(String, Range<usize>, usize)> =
-    Lazy::new(|| Mutex::new(HashMap::new()));
-
-pub static MACRO_MAP: SymbolTable<(String, MacroContent)> =
-    Lazy::new(|| Mutex::new(HashMap::new()));
-
-pub static START_LOCATION: Lazy<Mutex<i64>> = Lazy::new(|| Mutex::new(100));
-
-pub static METADATA_STR: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(String::from("")));
+//                              name    data
+pub type SymbolTable<T> = HashMap<String, T>;
+/// The file of constants defined with `-D`, which have no place in a source file
+pub const COMMAND_LINE: &str = "<command line>";
+
+//                        file      place     value
+pub type ConstMap = SymbolTable<(String, Range<usize>, i64)>;
+//                        file      place     value
+pub type LabelMap = SymbolTable<(String, Range<usize>, usize)>;
+
+pub type MacroMap = SymbolTable<(String, MacroContent)>;
+//                         file      place     replacement
+pub type DefineMap = SymbolTable<(String, Range<usize>, Vec<TokenKind>)>;
+
+// names referenced anywhere in the program, checked by the unused-* lints
+#[derive(Debug, Clone, Default)]
+pub struct SymbolUses {
+    pub constants: HashSet<String>,
+    pub labels: HashSet<String>,
+    pub macros: HashSet<String>,
+}
 
 // - Option<String>: messages about similar entries found (if any)
-// - Vec<(String, Range<usize>)>: (file, place) tuples for similar entries
-pub fn find_similar_entries(input: &str) -> (Option<String>, Vec<(String, Range<usize>)>) {
-    let mut messages = Vec::new();
-    let mut results = Vec::new();
-    let threshold = 3;
-
-    let v_map = V_MAP.lock().unwrap();
-    let label_map = LABEL_MAP.lock().unwrap();
-    let macro_map = MACRO_MAP.lock().unwrap();
-
-    let similar_v: Vec<String> = v_map
-        .keys()
-        .filter(|key| levenshtein(input, key) <= threshold)
-        .cloned()
-        .collect();
-    if !similar_v.is_empty() {
-        messages.push(format!(
-            "similar constants were found: {}",
-            similar_v
-                .iter()
-                .map(|s| s.magenta().to_string())
-                .collect::<Vec<_>>()
-                .join(", ")
-        ));
-        for key in similar_v {
-            if let Some((file, place, _)) = v_map.get(&key) {
-                results.push((file.clone(), place.clone()));
+// - Vec<Label>: where each of the similar entries was declared
+impl Assembler {
+    pub fn find_similar_entries(&self, input: &str) -> (Option<String>, Vec<Label>) {
+        let mut messages = Vec::new();
+        let mut results = Vec::new();
+        let threshold = 3;
+
+        let v_map = &self.v_map;
+        let label_map = &self.label_map;
+        let macro_map = &self.macro_map;
+
+        let similar_v: Vec<String> = v_map
+            .keys()
+            .filter(|key| levenshtein(input, key) <= threshold)
+            .cloned()
+            .collect();
+        if !similar_v.is_empty() {
+            messages.push(format!(
+                "similar constants were found: {}",
+                similar_v
+                    .iter()
+                    .map(|s| s.magenta().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+            for key in similar_v {
+                if let Some((file, place, _)) = v_map.get(&key).filter(|c| c.0 != COMMAND_LINE) {
+                    results.push(Label {
+                        file: file.clone(),
+                        span: place.clone(),
+                        message: Some(format!("constant `{key}` declared here")),
+                    });
+                }
             }
         }
-    }
 
-    let similar_labels: Vec<String> = label_map
-        .keys()
-        .filter(|key| levenshtein(input, key) <= threshold)
-        .cloned()
-        .collect();
-    if !similar_labels.is_empty() {
-        messages.push(format!(
-            "similar labels were found: {}",
-            similar_labels
-                .iter()
-                .map(|s| s.magenta().to_string())
-                .collect::<Vec<_>>()
-                .join(", ")
-        ));
-        for key in similar_labels {
-            if let Some((file, place, _)) = label_map.get(&key) {
-                results.push((file.clone(), place.clone()));
+        let similar_labels: Vec<String> = label_map
+            .keys()
+            .filter(|key| levenshtein(input, key) <= threshold)
+            .cloned()
+            .collect();
+        if !similar_labels.is_empty() {
+            messages.push(format!(
+                "similar labels were found: {}",
+                similar_labels
+                    .iter()
+                    .map(|s| s.magenta().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+            for key in similar_labels {
+                if let Some((file, place, _)) = label_map.get(&key) {
+                    results.push(Label {
+                        file: file.clone(),
+                        span: place.clone(),
+                        message: Some(format!("label `{key}` declared here")),
+                    });
+                }
             }
         }
-    }
 
-    let similar_macros: Vec<String> = macro_map
-        .keys()
-        .filter(|key| levenshtein(input, key) <= threshold)
-        .cloned()
-        .collect();
-    if !similar_macros.is_empty() {
-        messages.push(format!(
-            "similar macros were found: {}",
-            similar_macros
-                .iter()
-                .map(|s| s.magenta().to_string())
-                .collect::<Vec<_>>()
-                .join(", ")
-        ));
-        for key in similar_macros {
-            if let Some((_, mac)) = macro_map.get(&key) {
-                results.push((mac.file.to_string(), mac.name.1.clone()));
+        let similar_macros: Vec<String> = macro_map
+            .keys()
+            .filter(|key| levenshtein(input, key) <= threshold)
+            .cloned()
+            .collect();
+        if !similar_macros.is_empty() {
+            messages.push(format!(
+                "similar macros were found: {}",
+                similar_macros
+                    .iter()
+                    .map(|s| s.magenta().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+            for key in similar_macros {
+                if let Some((_, mac)) = macro_map.get(&key) {
+                    results.push(Label {
+                        file: mac.file.to_string(),
+                        span: mac.name.1.clone(),
+                        message: Some(format!("macro `{key}` declared here")),
+                    });
+                }
             }
         }
+
+        if messages.is_empty() {
+            (None, results)
+        } else {
+            (Some(messages.join(", ")), results)
+        }
     }
 
-    if messages.is_empty() {
-        (None, results)
-    } else {
-        (Some(messages.join(", ")), results)
+    pub fn print_symbol_tables(&self) {
+        print_hashmap("Constant map", &self.v_map);
+        print_hashmap("Label map", &self.label_map);
+        print_hashmap("Macro map", &self.macro_map);
     }
 }
-use prettytable::{row, Table};
-pub fn print_symbol_tables() {
-    let v_map = V_MAP.lock().unwrap();
-    let l_map = LABEL_MAP.lock().unwrap();
-    let m_map = MACRO_MAP.lock().unwrap();
-    print_hashmap("Constant map", &v_map);
-    print_hashmap("Label map", &l_map);
-    print_hashmap("Macro map", &m_map);
-}
 use crate::tok_print::get_custom_format;
+use prettytable::{row, Table};
 fn print_hashmap<K, V>(name: &str, map: &HashMap<K, V>)
 where
     K: std::fmt::Debug,
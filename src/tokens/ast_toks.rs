@@ -38,6 +38,27 @@ pub enum ArgumentType {
     Label,
 }
 
+/// A directive that puts out words, with the words worked out when its
+/// operands were read
+///
+/// They are kept on the directive itself, so every copy of a line made by a
+/// macro or a repeat block keeps its own.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PlacedDirective {
+    pub name: String,
+    pub words: DirectiveWords,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum DirectiveWords {
+    /// `count` copies of `value`, for .org, .pad, .space, .align and .fill
+    Fill { count: usize, value: i16 },
+    /// The values of a .word, with their labels looked up in codegen
+    Values(Vec<WordValue>),
+    /// The contents of an .incbin
+    Binary(Vec<i16>),
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum InstructionArgument {
     Mem(MemAddr),
@@ -48,9 +69,19 @@ pub enum InstructionArgument {
     MacroIdent(String),
 }
 
+// a macro call or repeat block that code was expanded from
+#[derive(Debug, PartialEq, Clone)]
+pub struct ExpansionSite {
+    pub name: String,
+    pub file: String,
+    pub span: Range<usize>,
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct InstructionData {
     pub expanded: bool,
+    // the macro calls this was expanded from, outermost first
+    pub expansion: Vec<ExpansionSite>,
     pub name: String,
     pub location: Range<usize>,
     pub operands: Vec<(InstructionArgument, Range<usize>)>,
@@ -12,6 +12,17 @@ impl ArgumentType {
             _ => None,
         }
     }
+    // the keyword from_string accepts for this type
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            ArgumentType::Mem => "mem",
+            ArgumentType::Imem => "imem",
+            ArgumentType::Ireg => "ireg",
+            ArgumentType::Imm => "imm",
+            ArgumentType::Reg => "reg",
+            ArgumentType::Label => "label",
+        }
+    }
     pub fn equals(&self, t: TokenKind) -> bool {
         use crate::ArgumentType::*;
         (*self == Mem && t.is_mem())
@@ -73,14 +84,6 @@ impl InstructionArgument {
             Reg(v) => *v as i64,
             IReg(v) => *v as i64,
             Imm(v) => *v,
-            Ident(s) => {
-                let vmap = V_MAP.lock().unwrap();
-                if let Some((_, _, v)) = vmap.get(s) {
-                    *v
-                } else {
-                    0
-                }
-            }
             Mem(m) => {
                 if let Some((v, _)) = m.data.first() {
                     v.get_value()
@@ -88,6 +91,7 @@ impl InstructionArgument {
                     0
                 }
             }
+            // identifiers are resolved against the assembler's symbol tables in codegen
             _ => 0,
         }
     }
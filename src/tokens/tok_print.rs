@@ -7,6 +7,7 @@ impl fmt::Display for TokenKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             TokenKind::IncludeFile(name) => write!(f, "include file \"{name}\""),
+            TokenKind::Included(tokens) => write!(f, "{} included tokens", tokens.len()),
             TokenKind::Newline => write!(f, "newline"),
             TokenKind::Whitespace => write!(f, "whitespace"),
             TokenKind::Tab => write!(f, "tab"),
@@ -44,6 +45,9 @@ impl fmt::Display for TokenKind {
             TokenKind::IntLit(value) => write!(f, "integer literal `{}`", value),
             TokenKind::MacroDef(value) => write!(f, "macro definition `{}`", value),
             TokenKind::Constant(value) => write!(f, "constant `{}`", value),
+            TokenKind::Define(name) => write!(f, "define `{name}`"),
+            TokenKind::Undef(name) => write!(f, "undef `{name}`"),
+            TokenKind::DefineCall(name) => write!(f, "define call `${name}`"),
             TokenKind::Ident(value) => write!(f, "identifier `{}`", value),
             TokenKind::Directive(value) => write!(f, "directive `{}`", value),
             TokenKind::MacroIdent(value) => write!(f, "macro identifier `{}`", value),
@@ -52,6 +56,7 @@ impl fmt::Display for TokenKind {
             TokenKind::MultiLineComment => write!(f, "comment"),
             TokenKind::Macro(content) => write!(f, "{}", content),
             TokenKind::Instruction(data) => write!(f, "{}", data),
+            TokenKind::Placed(placed) => write!(f, "directive `{}`", placed.name),
             TokenKind::Label(value) => write!(f, "label `{}`", value),
             TokenKind::Mem(token) => write!(f, "memory address\n{}", token),
             TokenKind::IIdent(value) => write!(f, "indirect identifier `{}`", value),
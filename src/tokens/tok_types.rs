@@ -6,6 +6,9 @@ pub enum TokenKind {
     #[regex(r#"@include\s+"([^"]+)""#, |lex| lex.slice()[8..].trim_start()[1..lex.slice()[8..].trim_start().len() - 1].to_string())]
     IncludeFile(String),
 
+    // the tokens of an @included file, read where the @include was
+    Included(Vec<(String, TokenKind, std::ops::Range<usize>)>),
+
     #[token("\n")]
     Newline,
 
@@ -117,6 +120,15 @@ pub enum TokenKind {
     #[regex(r"macro_rules!", |lex| lex.slice().to_string())]
     MacroDef(String),
 
+    #[regex(r"@define([ \t]+[a-zA-Z_][a-zA-Z0-9_]*)?", |lex| lex.slice()[7..].trim().to_string())]
+    Define(String),
+
+    #[regex(r"@undef([ \t]+[a-zA-Z_][a-zA-Z0-9_]*)?", |lex| lex.slice()[6..].trim().to_string())]
+    Undef(String),
+
+    #[regex(r"\$[a-zA-Z_][a-zA-Z0-9_]*", |lex| lex.slice()[1..].to_string())]
+    DefineCall(String),
+
     #[regex("const[ ]+[a-zA-Z_][a-zA-Z0-9_]*", |lex| lex.slice()[6..].trim().to_string())]
     Constant(String),
 
@@ -145,6 +157,8 @@ pub enum TokenKind {
 
     Instruction(InstructionData),
 
+    Placed(PlacedDirective),
+
     Label(String),
 
     Mem(MemAddr),
@@ -156,16 +170,18 @@ pub enum TokenKind {
     Imm(i64),
     Expr(i64),
 }
-fn parse_content(content: &str) -> i64 {
+// a literal too big for 64 bits is lexed as an error, which the first pass
+// reports with the literal's text
+fn parse_content(content: &str) -> Option<i64> {
     if content.starts_with("0x") || content.starts_with("0X") {
-        i64::from_str_radix(&content[2..], 16).unwrap()
+        i64::from_str_radix(&content[2..], 16).ok()
     } else if content.starts_with("0b") || content.starts_with("0B") {
-        i64::from_str_radix(&content[2..], 2).unwrap()
+        i64::from_str_radix(&content[2..], 2).ok()
     } else if content.starts_with("0o") || content.starts_with("0O") {
-        i64::from_str_radix(&content[2..], 8).unwrap()
+        i64::from_str_radix(&content[2..], 8).ok()
     } else if content.starts_with("'") && content.ends_with("'") {
         let char_content = &content[1..content.len() - 1];
-        if char_content.len() == 1 {
+        Some(if char_content.len() == 1 {
             char_content.chars().next().unwrap() as i64
         } else if char_content.starts_with('\\') {
             match char_content {
@@ -180,11 +196,11 @@ fn parse_content(content: &str) -> i64 {
             }
         } else {
             -1
-        }
+        })
     } else if content.chars().all(|c| c.is_ascii_digit() || c == '-') {
-        content.parse::<i64>().unwrap()
+        content.parse::<i64>().ok()
     } else {
-        -1
+        Some(-1)
     }
 }
 fn parse_string(s: &str) -> String {
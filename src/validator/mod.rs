@@ -1,5 +1,4 @@
 pub mod validator_core;
-pub mod validator_error;
 pub mod validator_ins;
+pub mod validator_lints;
 pub mod validator_macro;
-pub use validator_error::*;
@@ -0,0 +1,184 @@
+use crate::*;
+use colored::*;
+use std::ops::Range;
+
+impl Assembler {
+    /// Reports `diagnostic` at the level `lint` is configured to
+    pub fn lint(&mut self, lint: Lint, mut diagnostic: Diagnostic) {
+        let lints = &self.options.lints;
+        match lints.level(lint) {
+            LintLevel::Allow => return,
+            LintLevel::Warn => {
+                if !lints.is_explicit(lint) {
+                    diagnostic = diagnostic.with_note(format!("`-W {lint}` is on by default"));
+                }
+            }
+            LintLevel::Deny => {
+                diagnostic.severity = Severity::Error;
+                let flag = if lints.is_explicit(lint) {
+                    lint.name()
+                } else {
+                    "warnings"
+                };
+                diagnostic = diagnostic.with_note(format!("`-D {flag}` was given"));
+            }
+        }
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Lints that only need the expanded tokens of one source
+    ///
+    /// This runs before labels are resolved, so that labels still mark where
+    /// control flow can re-enter after a `hlt` or `jmp`.
+    pub fn lint_tokens(&mut self, toks: &[(String, TokenKind, Range<usize>)]) {
+        // the instruction that ended the reachable code, if nothing has reset it since
+        let mut flow_end: Option<(String, Range<usize>, String)> = None;
+        let mut toks_iter = toks.iter().peekable();
+        while let Some((fname, tok, span)) = toks_iter.next() {
+            match tok {
+                TokenKind::Instruction(ins) => {
+                    if let Some((end_file, end_span, end_name)) = flow_end.take() {
+                        self.lint(
+                            Lint::UnreachableCode,
+                            Diagnostic::warning(Lint::UnreachableCode, "unreachable instruction")
+                                .at(fname, span.clone())
+                                .with_label(
+                                    &end_file,
+                                    end_span,
+                                    Some(format!(
+                                        "any code following this `{end_name}` is unreachable"
+                                    )),
+                                ),
+                        );
+                    }
+                    let name = ins.name.to_lowercase();
+                    if name == "hlt" || name == "jmp" {
+                        flow_end = Some((fname.to_string(), span.clone(), name));
+                    }
+                    self.lint_instruction(fname, ins);
+                }
+                TokenKind::Label(name) => {
+                    flow_end = None;
+                    if let Some((file, place, _)) = self.v_map.get(name) {
+                        let constant = (file.to_string(), place.clone());
+                        self.lint(
+                            Lint::LabelShadowsConstant,
+                            Diagnostic::warning(
+                                Lint::LabelShadowsConstant,
+                                format!(
+                                    "label `{}` has the same name as a constant",
+                                    name.magenta()
+                                ),
+                            )
+                            .at(fname, span.clone())
+                            .with_declaration(
+                                &constant.0,
+                                constant.1,
+                                "constant declared here",
+                            ),
+                        );
+                    }
+                }
+                TokenKind::Directive(data) => {
+                    flow_end = None;
+                    if data.trim() == "pad" {
+                        if let Some((_, TokenKind::IntLit(0), pad_span)) = toks_iter.peek() {
+                            self.lint(
+                                Lint::ZeroPad,
+                                Diagnostic::warning(Lint::ZeroPad, "`.pad 0` has no effect")
+                                    .at(fname, span.start..pad_span.end)
+                                    .with_help("remove this directive"),
+                            );
+                        }
+                    }
+                }
+                TokenKind::Ident(name) => {
+                    self.uses.labels.insert(name.to_string());
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn lint_instruction(&mut self, fname: &str, ins: &InstructionData) {
+        // immediates are sign-magnitude, so the top bit of an 8-bit value is its sign
+        let signed = matches!(
+            ins.name.to_lowercase().as_str(),
+            "add" | "mov" | "nand" | "div" | "cmp" | "push"
+        );
+        for (arg, place) in &ins.operands {
+            match arg {
+                InstructionArgument::Ident(name) => {
+                    self.uses.labels.insert(name.to_string());
+                }
+                InstructionArgument::Mem(m) => {
+                    for (tok, _) in &m.data {
+                        if let TokenKind::Ident(name) = tok {
+                            self.uses.labels.insert(name.to_string());
+                        }
+                    }
+                }
+                InstructionArgument::Imm(v) if signed && (128..=255).contains(v) => {
+                    let read_as = -(v & 0x7f);
+                    self.lint(
+                        Lint::ImmediateTruncation,
+                        Diagnostic::warning(
+                            Lint::ImmediateTruncation,
+                            format!(
+                                "immediate {} has its sign bit set and will be read as {}",
+                                v.to_string().magenta(),
+                                read_as.to_string().magenta()
+                            ),
+                        )
+                        .at(fname, place.clone())
+                        .with_help(format!(
+                            "immediates range from -127 to 127 for `{}`",
+                            ins.name
+                        )),
+                    );
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Lints that need the whole program, run once assembly has finished
+    pub fn lint_unused(&mut self) {
+        let mut unused = Vec::new();
+        for (name, (file, place, _)) in &self.v_map {
+            // a build passes the same -D flags to every file, used or not
+            if !self.uses.constants.contains(name) && file != COMMAND_LINE {
+                unused.push((Lint::UnusedConstant, "constant", name, file, place.clone()));
+            }
+        }
+        for (name, (file, place, _)) in &self.label_map {
+            if !self.uses.labels.contains(name) {
+                unused.push((Lint::UnusedLabel, "label", name, file, place.clone()));
+            }
+        }
+        for (name, (_, mac)) in &self.macro_map {
+            if !self.uses.macros.contains(name) {
+                unused.push((
+                    Lint::UnusedMacro,
+                    "macro",
+                    name,
+                    &mac.file,
+                    mac.name.1.clone(),
+                ));
+            }
+        }
+        // the symbol tables are unordered, so sort to report in source order
+        unused.sort_by(|a, b| (a.3, a.4.start).cmp(&(b.3, b.4.start)));
+
+        let warnings: Vec<(Lint, Diagnostic)> = unused
+            .into_iter()
+            .map(|(lint, kind, name, file, place)| {
+                let message = format!("{kind} `{}` is never used", name.magenta());
+                (lint, Diagnostic::warning(lint, message).at(file, place))
+            })
+            .collect();
+        for (lint, diagnostic) in warnings {
+            self.lint(lint, diagnostic);
+        }
+    }
+}
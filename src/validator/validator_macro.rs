@@ -2,13 +2,12 @@ use crate::*;
 use colored::*;
 use std::collections::HashMap;
 use std::ops::Range;
-type ExpandResult = Result<Vec<(String, TokenKind, Range<usize>)>, Vec<MacroValidatorError>>;
+type ExpandResult = Result<Vec<(String, TokenKind, Range<usize>)>, Vec<Diagnostic>>;
 impl MacroContent {
     pub fn expand(
         &self,
         default_span: &Range<usize>,
-        err_file: &String,
-        orig_data: &String,
+        err_file: &str,
         toks: &[(TokenKind, Range<usize>)], // incoming macro args
     ) -> ExpandResult {
         // okay... here, I need to check first if the token types of the input
@@ -32,15 +31,12 @@ impl MacroContent {
                 token if token.is_ident() => Some(ArgumentType::Label),
                 TokenKind::Comma => None,
                 _ => {
-                    errs.push(MacroValidatorError {
-                        err_file: err_file.to_string(),
-                        err_input: self.full_data.to_string(),
-                        err_message: format!("a {token} is not a valid macro argument"),
-                        help: None,
-                        orig_input: orig_data.to_string(),
-                        orig_pos: span.clone(),
-                        mac: self.clone(),
-                    });
+                    errs.push(self.expansion_error(
+                        DiagnosticCode::MacroArguments,
+                        err_file,
+                        span.clone(),
+                        format!("a {token} is not a valid macro argument"),
+                    ));
                     return Err(errs);
                 }
             };
@@ -64,46 +60,37 @@ impl MacroContent {
             } else {
                 "arguments"
             };
-            errs.push(MacroValidatorError {
-                err_file: err_file.to_string(),
-                err_input: self.full_data.to_string(),
-                err_message: format!(
+            errs.push(self.expansion_error(
+                DiagnosticCode::MacroArguments,
+                err_file,
+                f.clone(),
+                format!(
                     "expected {} {word}, found {}",
                     self.parameters.len(),
                     parsed_toks.len()
                 ),
-                help: None,
-                orig_input: orig_data.to_string(),
-                orig_pos: f.clone(),
-                mac: self.clone(),
-            });
+            ));
         }
         for (index, (_, arg, _)) in self.parameters.iter().enumerate() {
             if let Some((d, _)) = parsed_toks.get(index) {
                 if *d == arg.arg_type {
                     continue;
                 } else {
-                    errs.push(MacroValidatorError {
-                        err_file: err_file.to_string(),
-                        err_input: self.full_data.to_string(),
-                        err_message: format!("expected {}, found {d}", arg.arg_type),
-                        help: None,
-                        orig_input: orig_data.to_string(), // this shouldn't panic
-                        orig_pos: parsed_toks.get(index).unwrap().1.clone(),
-                        mac: self.clone(),
-                    });
+                    errs.push(self.expansion_error(
+                        DiagnosticCode::MacroArguments,
+                        err_file,
+                        parsed_toks.get(index).unwrap().1.clone(),
+                        format!("expected {}, found {d}", arg.arg_type),
+                    ));
                     return Err(errs);
                 }
             } else {
-                errs.push(MacroValidatorError {
-                    err_file: err_file.to_string(),
-                    err_input: self.full_data.to_string(),
-                    err_message: String::from("an incorrect number of arguments were supplied"),
-                    help: None, // borrow checker is yappin
-                    orig_input: orig_data.to_string(),
-                    orig_pos: f.clone(),
-                    mac: self.clone(),
-                });
+                errs.push(self.expansion_error(
+                    DiagnosticCode::MacroArguments,
+                    err_file,
+                    f.clone(),
+                    String::from("an incorrect number of arguments were supplied"),
+                ));
             }
         } // we need a hashmap of type ident names, TokenKind to record arguments
         if !errs.is_empty() {
@@ -124,8 +111,6 @@ impl MacroContent {
             }
         }
 
-        // whenever the err input and orig input are the same, it is because the error cannot
-        // occur across files
         let mut new_elems = Vec::new();
         for (element, span) in &self.body {
             if let TokenKind::MacroIdent(name) = element {
@@ -133,18 +118,15 @@ impl MacroContent {
                     new_elems.push((self.file.to_string(), v.clone(), span.clone()));
                     continue;
                 } else {
-                    errs.push(MacroValidatorError {
-                        err_file: self.file.to_string(),
-                        err_input: read_file(&self.file.to_string()),
-                        err_message: format!(
+                    errs.push(self.expansion_error(
+                        DiagnosticCode::MacroArguments,
+                        &self.file,
+                        span.clone(),
+                        format!(
                             "{} was not an argument supplied in the macro parameters",
                             name.magenta()
                         ),
-                        help: None, // borrow checker is yappin
-                        orig_input: read_file(&self.file.to_string()),
-                        orig_pos: span.clone(),
-                        mac: self.clone(),
-                    });
+                    ));
                 }
             } else if let TokenKind::Instruction(contents) = element {
                 let mut ins_args = Vec::new();
@@ -155,39 +137,40 @@ impl MacroContent {
                             continue;
                         } else {
                             // we could make this a variable earlier and just send it
-                            errs.push(MacroValidatorError {
-                                err_file: self.file.to_string(),
-                                err_input: read_file(&self.file.to_string()),
-                                err_message: format!(
+                            errs.push(self.expansion_error(
+                                DiagnosticCode::MacroArguments,
+                                &self.file,
+                                place.clone(),
+                                format!(
                                     "{} was not an argument supplied in the macro parameters",
                                     name.magenta()
                                 ),
-                                help: None, // borrow checker is yappin
-                                orig_input: read_file(&self.file.to_string()),
-                                orig_pos: place.clone(),
-                                mac: self.clone(),
-                            });
+                            ));
                         }
                     }
                     ins_args.push((thing.clone(), place.clone()));
                 }
                 let reconstruct = InstructionData {
                     expanded: true,
+                    expansion: vec![ExpansionSite {
+                        name: self.name.0.to_string(),
+                        file: err_file.to_string(),
+                        span: f.clone(),
+                    }],
                     name: contents.name.to_string(),
                     operands: ins_args,
                     location: span.clone(),
                 };
                 if let Err(e) = reconstruct.is_valid() {
-                    errs.push(MacroValidatorError {
-                        err_file: self.file.to_string(),
-                        err_input: read_file(&self.file.to_string()), // these are dup'd as it is
-                        err_message: e.1,
-                        help: e.2,
-                        orig_input: read_file(&self.file.to_string()), // these are dup'd as it is
-                        // something in the macro
-                        orig_pos: e.0.unwrap_or_else(|| span.clone()),
-                        mac: self.clone(),
-                    });
+                    errs.push(
+                        self.expansion_error(
+                            DiagnosticCode::InvalidInstruction,
+                            &self.file,
+                            e.0.unwrap_or_else(|| span.clone()),
+                            e.1,
+                        )
+                        .with_help_opt(e.2),
+                    );
                 }
                 new_elems.push((
                     self.file.to_string(),
@@ -204,18 +187,15 @@ impl MacroContent {
                     ));
                     continue;
                 } else {
-                    errs.push(MacroValidatorError {
-                        err_file: self.file.to_string(),
-                        err_input: read_file(&self.file.to_string()),
-                        err_message: format!(
+                    errs.push(self.expansion_error(
+                        DiagnosticCode::MacroArguments,
+                        &self.file,
+                        span.clone(),
+                        format!(
                             "`{}` must be an identifier specified in macro arguments",
                             label_name.magenta()
                         ),
-                        help: None, // borrow checker is yappin
-                        orig_input: read_file(&self.file.to_string()),
-                        orig_pos: span.clone(),
-                        mac: self.clone(),
-                    });
+                    ));
                 }
             }
             new_elems.push((self.file.to_string(), element.clone(), span.clone()));
@@ -225,4 +205,23 @@ impl MacroContent {
         }
         Ok(new_elems)
     }
+
+    // the macro's signature is pointed at alongside every error in its expansion
+    fn expansion_error(
+        &self,
+        code: DiagnosticCode,
+        file: &str,
+        span: Range<usize>,
+        message: String,
+    ) -> Diagnostic {
+        let end = match self.parameters.last() {
+            Some((_, _, v)) => v.end,
+            None => self.name.1.end,
+        };
+        Diagnostic::error(code, message).at(file, span).with_label(
+            &self.file,
+            self.name.1.start..end,
+            Some(format!("in expansion of macro `{}`", self.name.0)),
+        )
+    }
 }
@@ -0,0 +1,42 @@
+mod common;
+
+use basm::*;
+use common::*;
+
+const CLASHING: &str = ".start [0x100]\nmain:\n    add r1, 1\nMain:\n    hlt\n";
+
+#[test]
+fn labels_clashing_once_upper_cased_are_errors() {
+    for output_format in [OutputFormat::CArray, OutputFormat::RustArray] {
+        let options = AssembleOptions {
+            output_format,
+            array_name: String::from("prog"),
+            ..AssembleOptions::default()
+        };
+        let errors = assemble_with("array_clash", CLASHING, &options).unwrap_err();
+        let errors: Vec<_> = errors.into_iter().filter(|d| d.is_error()).collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, DiagnosticCode::DuplicateLabel);
+        assert!(errors[0].message.contains("`PROG_LABEL_MAIN`"));
+    }
+
+    // other formats keep the names as they are
+    assemble_ok("array_clash_bin", CLASHING);
+}
+
+#[test]
+fn array_outputs_name_every_label() {
+    let options = AssembleOptions {
+        output_format: OutputFormat::RustArray,
+        ..AssembleOptions::default()
+    };
+    let program = assemble_with(
+        "array_labels",
+        ".start [0x100]\nmain:\n    add r1, 1\nloop:\n    hlt\n",
+        &options,
+    )
+    .unwrap();
+    let out = program.render_rust_array("program");
+    assert!(out.contains("pub const PROGRAM_LABEL_MAIN: u16 = 0x0100;"));
+    assert!(out.contains("pub const PROGRAM_LABEL_LOOP: u16 = 0x0101;"));
+}
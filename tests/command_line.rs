@@ -0,0 +1,72 @@
+mod common;
+
+use basm::*;
+use common::*;
+
+fn with_defines(test: &str, defines: &[(&str, i64)]) -> Result<AssembledProgram, Vec<Diagnostic>> {
+    let options = AssembleOptions {
+        defines: defines
+            .iter()
+            .map(|(name, value)| (name.to_string(), *value))
+            .collect(),
+        ..AssembleOptions::default()
+    };
+    assemble_with(test, ".start [0x100]\nadd r1, (X)\nhlt\n", &options)
+}
+
+#[test]
+fn defined_constants_are_used() {
+    let program = with_defines("cli_define", &[("X", 7)]).unwrap();
+    assert_eq!(program.binary, [0x1307, 0]);
+}
+
+#[test]
+fn defining_twice_is_an_error() {
+    let errors = with_defines("cli_define_twice", &[("X", 7), ("X", 4)]).unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].code, DiagnosticCode::DuplicateConstant);
+    assert!(errors[0].message.contains("7 and then 4"));
+}
+
+#[test]
+fn command_line_constants_have_no_file() {
+    let program = with_defines("cli_define_symbols", &[("X", 7)]).unwrap();
+    assert!(program
+        .render_symbols(SymbolFormat::Text)
+        .contains("const\tX\t7\t-\t-\n"));
+    assert!(program
+        .render_symbols(SymbolFormat::Json)
+        .contains("{\"name\":\"X\",\"value\":7,\"file\":null,\"line\":null}"));
+    assert!(program.render_listing().contains("X  command line  (7)"));
+}
+
+#[test]
+fn defines_follow_the_order_given() {
+    let source = sources("cli_define_order", &[("main.asm", ".start [0x100]\nhlt\n")]);
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_basm"))
+        .args([&source, "-o", &format!("{source}.bin")])
+        .args(["-D", "X=4", "--define", "X=7"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("as 4 and then 7"), "{stdout}");
+}
+
+#[test]
+fn overflowing_defines_are_argument_errors() {
+    let source = sources(
+        "cli_define_overflow",
+        &[("main.asm", ".start [0x100]\nhlt\n")],
+    );
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_basm"))
+        .args([&source, "-o", &format!("{source}.bin")])
+        .args(["-D", "X=1<<64"])
+        .output()
+        .unwrap();
+    // clap's usage error, rather than a panic
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("invalid value for `X`"), "{stderr}");
+    assert!(!stderr.contains("panicked"), "{stderr}");
+}
@@ -0,0 +1,46 @@
+#![allow(dead_code)]
+
+use basm::*;
+use std::path::PathBuf;
+
+/// A directory of its own for a test, so tests can run side by side
+pub fn scratch(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("basm-{}-{test}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Writes each `(name, contents)` into the test's directory, and returns the
+/// path of the first
+pub fn sources(test: &str, files: &[(&str, &str)]) -> String {
+    let dir = scratch(test);
+    for (name, contents) in files {
+        let path = dir.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+    dir.join(files[0].0).to_string_lossy().to_string()
+}
+
+pub fn assemble_with(
+    test: &str,
+    source: &str,
+    options: &AssembleOptions,
+) -> Result<AssembledProgram, Vec<Diagnostic>> {
+    assemble(&sources(test, &[("main.asm", source)]), options)
+}
+
+pub fn assemble_ok(test: &str, source: &str) -> AssembledProgram {
+    match assemble_with(test, source, &AssembleOptions::default()) {
+        Ok(program) => program,
+        Err(errors) => panic!("{test} failed to assemble: {errors:#?}"),
+    }
+}
+
+pub fn assemble_err(test: &str, source: &str) -> Vec<Diagnostic> {
+    match assemble_with(test, source, &AssembleOptions::default()) {
+        Ok(program) => panic!("{test} assembled to {:04x?}", program.binary),
+        Err(errors) => errors.into_iter().filter(|d| d.is_error()).collect(),
+    }
+}
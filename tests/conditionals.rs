@@ -0,0 +1,13 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn blocks_nest_inside_left_out_branches() {
+    let program = assemble_ok(
+        "cond_nested_skipped",
+        ".start [0x100]\n.if 0\n    .if 1\n        add r1, 1\n    .endif\n\
+         \x20   .ifdef X\n        add r1, 2\n    .endif\n.else\n    add r1, 3\n.endif\nhlt\n",
+    );
+    assert_eq!(program.binary, [0x1303, 0]);
+}
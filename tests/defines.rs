@@ -0,0 +1,49 @@
+mod common;
+
+use basm::*;
+use common::*;
+
+#[test]
+fn defines_are_replaced_before_expressions() {
+    let program = assemble_ok(
+        "define_expressions",
+        "@define VAL 3\n@define ACC r1\n.start [0x100]\n\
+         add ACC, (VAL + 1)\n.fill VAL, (VAL * 2)\nhlt\n",
+    );
+    assert_eq!(program.binary, [0x1304, 6, 6, 6, 0]);
+}
+
+#[test]
+fn defines_work_in_conditions() {
+    let program = assemble_ok(
+        "define_conditions",
+        "@define VAL 3\n.start [0x100]\n\
+         .if (VAL - 3)\n    add r1, 1\n.elif $VAL\n    add r1, 2\n.endif\nhlt\n",
+    );
+    assert_eq!(program.binary, [0x1302, 0]);
+}
+
+#[test]
+fn defines_work_in_addresses() {
+    let program = assemble_ok(
+        "define_addresses",
+        "@define BUF (0x40 + 3)\n.start [0x100]\nld r2, [BUF]\nhlt\n",
+    );
+    assert_eq!(program.binary, [0x6443, 0]);
+}
+
+#[test]
+fn left_out_branches_are_not_replaced() {
+    let program = assemble_ok(
+        "define_skipped",
+        ".start [0x100]\n.if 0\n    add $MISSING, 1\n@define X 1\n.endif\nhlt\n",
+    );
+    assert_eq!(program.binary, [0]);
+}
+
+#[test]
+fn unknown_defines_are_errors() {
+    let errors = assemble_err("define_unknown", ".start [0x100]\nadd $ACC, 1\nhlt\n");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].code, DiagnosticCode::UnknownDefine);
+}
@@ -0,0 +1,36 @@
+mod common;
+
+use basm::*;
+use common::*;
+
+const FOUR_ERRORS: &str = ".start [0x100]\nfoo\nbar\nbaz\nqux\nhlt\n";
+
+#[test]
+fn errors_past_the_limit_are_counted_in_a_note() {
+    let options = AssembleOptions {
+        error_limit: Some(2),
+        ..AssembleOptions::default()
+    };
+    let diagnostics = assemble_with("error_limit", FOUR_ERRORS, &options).unwrap_err();
+    assert_eq!(diagnostics.iter().filter(|d| d.is_error()).count(), 2);
+    let note = diagnostics
+        .iter()
+        .find(|d| d.code == DiagnosticCode::ErrorLimit)
+        .unwrap();
+    assert!(
+        note.message.contains("2 more not shown"),
+        "{}",
+        note.message
+    );
+}
+
+#[test]
+fn no_note_within_the_limit() {
+    let mut diagnostics = assemble_err("error_limit_within", FOUR_ERRORS);
+    assert_eq!(diagnostics.len(), 4);
+    limit_errors(&mut diagnostics, 4);
+    assert_eq!(diagnostics.len(), 4);
+    limit_errors(&mut diagnostics, 3);
+    assert_eq!(diagnostics.len(), 4);
+    assert_eq!(diagnostics[3].code, DiagnosticCode::ErrorLimit);
+}
@@ -0,0 +1,36 @@
+mod common;
+
+use basm::*;
+use common::*;
+
+#[test]
+fn literals_too_large_are_errors() {
+    let errors = assemble_err(
+        "literal_too_large",
+        ".start [0x100]\nmov r1, 99999999999999999999\nhlt\n",
+    );
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].code, DiagnosticCode::InvalidToken);
+    assert!(errors[0].message.contains("is too large"));
+
+    let errors = assemble_err(
+        "hex_too_large",
+        ".start [0x100]\n.fill 1, 0x1FFFFFFFFFFFFFFFF\nhlt\n",
+    );
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].code, DiagnosticCode::InvalidToken);
+}
+
+#[test]
+fn overflowing_expressions_are_errors() {
+    for (test, expr) in [
+        ("shift_too_far", "(1 << 70)"),
+        ("shift_negative", "(4 >> -1)"),
+        ("add_overflow", "(0x7FFFFFFFFFFFFFFF + 1)"),
+        ("mul_overflow", "(0x100000000 * 0x100000000)"),
+    ] {
+        let errors = assemble_err(test, &format!("const A = {expr}\n.start [0x100]\nhlt\n"));
+        assert_eq!(errors.len(), 1, "{expr}");
+        assert_eq!(errors[0].code, DiagnosticCode::InvalidExpression, "{expr}");
+    }
+}
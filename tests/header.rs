@@ -0,0 +1,112 @@
+use basm::*;
+
+fn header(version: u16, metadata: &str) -> BinaryHeader {
+    BinaryHeader {
+        version,
+        entry_point: 0x104,
+        load_address: 0x100,
+        code_length: 3,
+        metadata: metadata.to_string(),
+    }
+}
+
+const CODE: [u8; 6] = [0x12, 0x02, 0xe3, 0x00, 0x00, 0x00];
+
+#[test]
+fn v2_round_trips() {
+    for metadata in ["", "odd", "even"] {
+        let written = header(2, metadata);
+        let bytes = written.write(&CODE);
+        assert_eq!(&bytes[..4], b"BELL");
+        let (read, code) = BinaryHeader::read(&bytes).unwrap();
+        assert_eq!(read, written);
+        assert_eq!(code, CODE);
+    }
+}
+
+#[test]
+fn v1_round_trips() {
+    for metadata in ["", "odd", "even"] {
+        // version 1 has one address for both, and no code length
+        let written = BinaryHeader {
+            load_address: 0x104,
+            ..header(1, metadata)
+        };
+        let bytes = written.write(&CODE);
+        assert_eq!(&bytes[..4], [0x01, 0x02, 0x01, 0x04]);
+        let (read, code) = BinaryHeader::read(&bytes).unwrap();
+        assert_eq!(read, written);
+        assert_eq!(code, CODE);
+    }
+}
+
+#[test]
+fn bad_magic_is_rejected() {
+    let mut bytes = header(2, "").write(&CODE);
+    bytes[0] = b'X';
+    assert_eq!(BinaryHeader::read(&bytes), Err(HeaderError::BadMagic));
+    assert_eq!(BinaryHeader::read(&[]), Err(HeaderError::BadMagic));
+}
+
+#[test]
+fn truncation_is_rejected() {
+    let bytes = header(2, "metadata").write(&CODE);
+    // cut inside the fixed fields, then inside the metadata
+    for len in [6, 12, 18, HEADER_V2_LEN + 3] {
+        assert_eq!(
+            BinaryHeader::read(&bytes[..len]),
+            Err(HeaderError::Truncated),
+            "cut at {len}"
+        );
+    }
+    let bytes = header(1, "metadata").write(&CODE);
+    for len in [3, 5, 9] {
+        assert_eq!(
+            BinaryHeader::read(&bytes[..len]),
+            Err(HeaderError::Truncated),
+            "cut at {len}"
+        );
+    }
+}
+
+#[test]
+fn missing_code_is_rejected() {
+    let bytes = header(2, "").write(&CODE);
+    // the checksum covers the code, so fix it up to get at the length check
+    let mut short = bytes[..bytes.len() - 2].to_vec();
+    let resealed = header(2, "").write(&CODE[..4]);
+    short[16..20].copy_from_slice(&resealed[16..20]);
+    assert_eq!(
+        BinaryHeader::read(&short),
+        Err(HeaderError::LengthMismatch {
+            expected: 6,
+            found: 4
+        })
+    );
+}
+
+#[test]
+fn crc_mismatch_is_rejected() {
+    let mut bytes = header(2, "meta").write(&CODE);
+    let last = bytes.len() - 1;
+    bytes[last] ^= 1;
+    assert!(matches!(
+        BinaryHeader::read(&bytes),
+        Err(HeaderError::ChecksumMismatch { .. })
+    ));
+}
+
+#[test]
+fn unknown_versions_are_rejected() {
+    let mut bytes = header(2, "").write(&CODE);
+    bytes[5] = 3;
+    assert_eq!(
+        BinaryHeader::read(&bytes),
+        Err(HeaderError::UnsupportedVersion(3))
+    );
+}
+
+#[test]
+fn bin_output_keeps_the_v1_header() {
+    assert_eq!(AssembleOptions::default().header, HeaderFormat::V1);
+}
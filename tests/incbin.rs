@@ -0,0 +1,41 @@
+mod common;
+
+use basm::*;
+use common::*;
+
+fn incbin(test: &str, line: &str) -> Result<AssembledProgram, Vec<Diagnostic>> {
+    let main = sources(
+        test,
+        &[("main.asm", &format!(".start [0x100]\n{line}\nhlt\n"))],
+    );
+    let dir = std::path::Path::new(&main).parent().unwrap();
+    std::fs::write(dir.join("blob.bin"), [1, 2, 3, 4, 5, 6]).unwrap();
+    assemble(&main, &AssembleOptions::default())
+}
+
+#[test]
+fn offset_and_length_pick_the_bytes() {
+    let program = incbin("incbin_slice", ".incbin \"blob.bin\", 2, 2").unwrap();
+    assert_eq!(program.binary, [0x0304, 0x0000]);
+}
+
+#[test]
+fn bad_counts_point_at_themselves() {
+    for (test, line, operand) in [
+        ("incbin_negative_offset", ".incbin \"blob.bin\", -1", "-1"),
+        (
+            "incbin_negative_length",
+            ".incbin \"blob.bin\", 0, -2",
+            "-2",
+        ),
+        ("incbin_extra", ".incbin \"blob.bin\", 0, 2, 4", "4"),
+    ] {
+        let errors = incbin(test, line).unwrap_err();
+        let errors: Vec<_> = errors.into_iter().filter(|d| d.is_error()).collect();
+        assert_eq!(errors.len(), 1, "{line}");
+        assert!(!errors[0].message.contains("file name"), "{line}");
+        let span = errors[0].primary.as_ref().unwrap().span.clone();
+        let source = format!(".start [0x100]\n{line}\nhlt\n");
+        assert!(source[span].contains(operand), "{line}");
+    }
+}
@@ -0,0 +1,110 @@
+mod common;
+
+use basm::*;
+use common::*;
+
+fn cycle_errors(test: &str, files: &[(&str, &str)]) -> Vec<Diagnostic> {
+    let main = sources(test, files);
+    let errors = assemble(&main, &AssembleOptions::default()).unwrap_err();
+    errors.into_iter().filter(|d| d.is_error()).collect()
+}
+
+#[test]
+fn self_include_is_a_cycle() {
+    let errors = cycle_errors(
+        "include_self",
+        &[("main.asm", "@include \"main.asm\"\nhlt\n")],
+    );
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].code, DiagnosticCode::IncludeCycle);
+}
+
+#[test]
+fn mutual_includes_are_a_cycle() {
+    let errors = cycle_errors(
+        "include_mutual",
+        &[
+            ("main.asm", ".start [0x100]\n@include \"b.asm\"\nhlt\n"),
+            ("b.asm", "@include \"sub/c.asm\"\n"),
+            ("sub/c.asm", "add r1, r2\n@include \"../main.asm\"\n"),
+        ],
+    );
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].code, DiagnosticCode::IncludeCycle);
+    // one label for each include that leads back round
+    assert_eq!(errors[0].secondary.len(), 2);
+}
+
+#[test]
+fn a_file_can_be_included_twice() {
+    let main = sources(
+        "include_diamond",
+        &[
+            (
+                "main.asm",
+                ".start [0x100]\n@include \"b.asm\"\n@include \"c.asm\"\nhlt\n",
+            ),
+            ("b.asm", "@include \"d.asm\"\n"),
+            ("c.asm", "@include \"d.asm\"\n"),
+            ("d.asm", "add r1, r2\n"),
+        ],
+    );
+    let program = assemble(&main, &AssembleOptions::default()).unwrap();
+    assert_eq!(program.binary, [0x1202, 0x1202, 0x0000]);
+}
+
+#[test]
+fn included_files_see_the_defines_above_the_include() {
+    let main = sources(
+        "include_defines",
+        &[
+            (
+                "main.asm",
+                ".start [0x100]\n@define X r1\n@include \"a.asm\"\n@undef X\n\
+                 @define X r2\n@include \"a.asm\"\nhlt\n",
+            ),
+            ("a.asm", "add X, 1\n"),
+        ],
+    );
+    let program = assemble(&main, &AssembleOptions::default()).unwrap();
+    assert_eq!(program.binary, [0x1301, 0x1501, 0x0000]);
+}
+
+#[test]
+fn conditions_see_constants_from_included_files() {
+    let main = sources(
+        "include_constants",
+        &[
+            (
+                "main.asm",
+                ".start [0x100]\n@include \"c.asm\"\n.if Y\n    add r1, 1\n.endif\n\
+                 .ifdef Y\n    add r1, 2\n.endif\n.ifdef there\n    add r1, 3\n.endif\nhlt\n",
+            ),
+            ("c.asm", "const Y = 1\nthere:\n"),
+        ],
+    );
+    let program = assemble(&main, &AssembleOptions::default()).unwrap();
+    assert_eq!(program.binary, [0x1301, 0x1302, 0x1303, 0x0000]);
+}
+
+#[test]
+fn listing_shows_the_include_before_the_included_lines() {
+    let main = sources(
+        "include_listing",
+        &[
+            (
+                "main.asm",
+                ".start [0x100]\nadd r1, 1\n@include \"b.asm\"\nadd r1, 3\nhlt\n",
+            ),
+            ("b.asm", "; b\nadd r1, 2\n"),
+        ],
+    );
+    let listing = assemble(&main, &AssembleOptions::default())
+        .unwrap()
+        .render_listing();
+    let include = listing.find("@include \"b.asm\"").unwrap();
+    let included = listing.find("add r1, 2").unwrap();
+    assert!(include < included, "{listing}");
+    // main.asm is only headed again once b.asm is done
+    assert_eq!(listing.matches("main.asm\n").count(), 2, "{listing}");
+}
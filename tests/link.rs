@@ -0,0 +1,165 @@
+mod common;
+
+use basm::*;
+use common::*;
+
+const MAIN: &str = "\
+.global main
+main:
+    lea r0, msg
+    jmp helper
+loop:
+    bz loop
+    hlt
+.word tab + 1
+";
+
+const LIB: &str = "\
+.global helper
+.global msg
+helper:
+    st local, r1
+    ret
+local:
+    .word msg
+msg:
+    .asciiz \"hi\"
+.global tab
+tab:
+.word msg + 1, msg - 1, 5
+";
+
+fn object_options() -> AssembleOptions {
+    AssembleOptions {
+        output_format: OutputFormat::Object,
+        ..AssembleOptions::default()
+    }
+}
+
+fn object(test: &str, source: &str) -> ObjectFile {
+    match assemble_with(test, source, &object_options()) {
+        Ok(program) => ObjectFile::from_program(&program),
+        Err(errors) => panic!("{test} failed to assemble: {errors:#?}"),
+    }
+}
+
+fn link_sources(
+    test: &str,
+    files: &[&str],
+    base: u16,
+) -> Result<AssembledProgram, Vec<Diagnostic>> {
+    let objects: Vec<(String, ObjectFile)> = files
+        .iter()
+        .enumerate()
+        .map(|(i, source)| (format!("{i}.o"), object(&format!("{test}_{i}"), source)))
+        .collect();
+    link(&objects, base, Some("main"))
+}
+
+#[test]
+fn objects_round_trip() {
+    let written = object("object_round_trip", LIB);
+    assert!(!written.relocations.is_empty());
+    let read = ObjectFile::read(&written.write()).unwrap();
+    assert_eq!(read, written);
+}
+
+#[test]
+fn broken_objects_are_rejected() {
+    let bytes = object("object_broken", LIB).write();
+    assert_eq!(ObjectFile::read(b"NOPE"), Err(ObjectError::BadMagic));
+    for len in [2, 8, 20, bytes.len() - 1] {
+        assert_eq!(
+            ObjectFile::read(&bytes[..len]).unwrap_err(),
+            ObjectError::Truncated
+        );
+    }
+}
+
+#[test]
+fn every_relocation_kind_is_recorded() {
+    let kinds: Vec<RelocationKind> = object("object_kinds", LIB)
+        .relocations
+        .iter()
+        .map(|reloc| reloc.kind)
+        .collect();
+    // the validator doesn't let `pop` take a label, so there's no Pop11 here
+    for kind in [RelocationKind::Store8, RelocationKind::Word16] {
+        assert!(kinds.contains(&kind), "no {kind:?} in {kinds:?}");
+    }
+    let kinds: Vec<RelocationKind> = object("object_kinds_main", MAIN)
+        .relocations
+        .iter()
+        .map(|reloc| reloc.kind)
+        .collect();
+    assert!(kinds.contains(&RelocationKind::Load9));
+    assert!(kinds.contains(&RelocationKind::Branch10));
+}
+
+#[test]
+fn relocations_fill_their_fields() {
+    use RelocationKind::*;
+    // the bits outside the field are kept
+    assert_eq!(Branch10.apply(0x7c00, 0x3ff), Some(0x7fff));
+    assert_eq!(Load9.apply(0x6400, 0x1ff), Some(0x65ff));
+    assert_eq!(Store8.apply(0x7007, 0xff), Some(0x77ff));
+    assert_eq!(Pop11.apply(0x5000, 0x7ff), Some(0x57ff));
+    // a .word keeps the amount added to the address
+    assert_eq!(Word16.apply(2, 0x100), Some(0x102));
+    assert_eq!(Word16.apply(-1, 0x100), Some(0xff));
+}
+
+#[test]
+fn relocations_out_of_range_are_rejected() {
+    use RelocationKind::*;
+    assert_eq!(Branch10.apply(0, 0x400), None);
+    assert_eq!(Load9.apply(0, 0x200), None);
+    assert_eq!(Store8.apply(0, 0x100), None);
+    assert_eq!(Pop11.apply(0, 0x800), None);
+    assert_eq!(Word16.apply(1, 0xffff), None);
+
+    // placed past what the load field of `lea` can reach
+    let errors = link_sources("link_overflow", &[MAIN, LIB], 0x1f0).unwrap_err();
+    assert!(errors
+        .iter()
+        .any(|e| e.code == DiagnosticCode::RelocationOverflow));
+}
+
+#[test]
+fn duplicate_globals_are_rejected() {
+    let errors = link_sources("link_duplicate", &[MAIN, LIB, LIB], 100).unwrap_err();
+    assert!(errors
+        .iter()
+        .any(|e| e.code == DiagnosticCode::DuplicateSymbol));
+}
+
+#[test]
+fn undefined_globals_are_rejected() {
+    let errors = link_sources("link_undefined", &[MAIN], 100).unwrap_err();
+    let undefined: Vec<&Diagnostic> = errors
+        .iter()
+        .filter(|e| e.code == DiagnosticCode::UndefinedSymbol)
+        .collect();
+    // msg, helper and tab
+    assert_eq!(undefined.len(), 3);
+}
+
+#[test]
+fn linking_matches_a_single_build() {
+    let linked = link_sources("link_whole", &[MAIN, LIB], 100).unwrap();
+    let whole = assemble_ok("link_whole_single", &format!(".start [100]\n{MAIN}{LIB}"));
+    assert_eq!(linked.binary, whole.binary);
+    assert_eq!(linked.entry_point, whole.label_map["main"].2 as i64);
+}
+
+#[test]
+fn addresses_are_rejected_in_objects() {
+    for directive in [".align 4", ".org 0x10"] {
+        let source = format!("main:\n    add r1, r2\n{directive}\n    hlt\n");
+        let errors = assemble_with("object_align", &source, &object_options()).unwrap_err();
+        assert!(
+            errors.iter().any(|e| e.message.contains("object file")),
+            "{directive} was accepted"
+        );
+    }
+}
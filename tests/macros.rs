@@ -0,0 +1,82 @@
+mod common;
+
+use basm::*;
+use common::*;
+
+#[test]
+fn nested_calls_expand() {
+    let program = assemble_ok(
+        "macro_nested",
+        "macro_rules! inc(r: reg) {\n    add %r, 1\n}\n\
+         macro_rules! inc2(a: reg, b: reg) {\n    inc!(%a)\n    inc!(%b)\n}\n\
+         .start [0x100]\ninc2!(r1, r2)\nhlt\n",
+    );
+    assert_eq!(program.binary.len(), 3);
+    assert_eq!(program.origins[1].expansion.len(), 2);
+}
+
+#[test]
+fn recursion_is_reported_at_once() {
+    // called twice per expansion, this would double every level
+    let errors = assemble_err(
+        "macro_recursive",
+        "macro_rules! boom() {\n    boom!()\n    boom!()\n}\n.start [0x100]\nboom!()\nhlt\n",
+    );
+    assert!(!errors.is_empty());
+    assert!(errors.iter().all(|e| e.message.contains("calls itself")));
+
+    let errors = assemble_err(
+        "macro_mutual",
+        "macro_rules! ping() {\n    pong!()\n}\nmacro_rules! pong() {\n    ping!()\n}\n\
+         .start [0x100]\nping!()\nhlt\n",
+    );
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].message.contains("calls itself"));
+}
+
+#[test]
+fn expansions_are_capped() {
+    // each level calls the next twice, for 2^18 calls in all
+    let mut source = String::from("macro_rules! m18() {\n    add r1, 1\n}\n");
+    for level in (0..18).rev() {
+        let next = level + 1;
+        source.push_str(&format!(
+            "macro_rules! m{level}() {{\n    m{next}!()\n    m{next}!()\n}}\n"
+        ));
+    }
+    source.push_str(".start [0x100]\nm0!()\nhlt\n");
+    let errors = assemble_err("macro_fan_out", &source);
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].message.contains("too many macro calls"));
+}
+
+// the line of each label an error points at, apart from its own
+fn label_lines(source: &str, error: &Diagnostic) -> Vec<usize> {
+    error
+        .secondary
+        .iter()
+        .map(|label| source[..label.span.start].matches('\n').count() + 1)
+        .collect()
+}
+
+#[test]
+fn nested_errors_point_at_every_call() {
+    let source = "macro_rules! ld2(a: reg, v: imm) {\n    mov %a, %v\n}\n\
+                  macro_rules! outer(a: reg) {\n    ld2!(%a, 5000)\n}\n\
+                  .start [0x100]\nouter!(r1)\nhlt\n";
+    let errors = assemble_err("macro_backtrace", source);
+    assert_eq!(errors.len(), 1);
+    // ld2's declaration, then the outer! call and the ld2! call in it
+    assert_eq!(label_lines(source, &errors[0]), [1, 8, 5]);
+}
+
+#[test]
+fn codegen_errors_point_at_every_call() {
+    let source = "macro_rules! j(l: ident) {\n    jmp %l\n}\n\
+                  macro_rules! outer() {\n    j!(nowhere)\n}\n\
+                  .start [0x100]\nouter!()\nhlt\n";
+    let errors = assemble_err("macro_codegen_backtrace", source);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].code, DiagnosticCode::UnknownLabel);
+    assert_eq!(label_lines(source, &errors[0]), [8, 5]);
+}
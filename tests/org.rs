@@ -0,0 +1,57 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn org_below_the_start_names_the_start() {
+    let errors = assemble_err("org_below_start", ".start [0x30]\n.org 0x20\nhlt\n");
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].message.contains("cannot move back to 0x20"));
+    assert_eq!(
+        errors[0].help.as_deref(),
+        Some("the program starts at 0x30, so nothing goes below it")
+    );
+
+    let errors = assemble_err(
+        "org_backwards",
+        ".start [0x30]\n.fill 4, 0\n.org 0x32\nhlt\n",
+    );
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].help.as_deref(),
+        Some("words from 0x32 to 0x33 have already been placed")
+    );
+}
+
+#[test]
+fn words_past_the_last_address_are_errors() {
+    let errors = assemble_err("org_past_end", ".start [0x100]\n.org 0x10000\nhlt\n");
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].message.contains("past the last address"));
+
+    // too big to allocate, were it placed
+    let errors = assemble_err("org_huge", ".start [0x100]\n.org 0x7fffffff\nhlt\n");
+    assert_eq!(errors.len(), 1);
+
+    for (test, line) in [
+        ("pad_past_end", ".pad 0x10000"),
+        ("fill_past_end", ".fill 0xff00, 1"),
+        ("align_past_end", ".align 0x7fffffff"),
+    ] {
+        let errors = assemble_err(test, &format!(".start [0x100]\n{line}\nhlt\n"));
+        assert_eq!(errors.len(), 1, "{line}");
+        assert!(
+            errors[0].message.contains("past the last address"),
+            "{line}"
+        );
+    }
+
+    // the last address itself can be used, and only the first word after it
+    // is reported
+    assemble_ok("org_last", ".start [0x100]\n.org 0xffff\nhlt\n");
+    let errors = assemble_err(
+        "org_overrun",
+        ".start [0x100]\n.org 0xffff\nhlt\nhlt\nhlt\n",
+    );
+    assert_eq!(errors.len(), 1);
+}
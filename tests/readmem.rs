@@ -0,0 +1,25 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn gaps_are_jumped_over() {
+    let program = assemble_ok(
+        "readmem_gaps",
+        ".start [0x100]\n    add r1, r2\n.org 0x104\n    add r1, 1\n\
+         .align 4\n.space 2\n.fill 2, 0\n.word 5\nhlt\n",
+    );
+    assert_eq!(
+        program.render_readmem(false),
+        "@0100\n1202\n@0104\n1301\n@010a\n0000\n0000\n0005\n0000\n"
+    );
+    assert!(program
+        .render_readmem(true)
+        .starts_with("@0100\n0001001000000010\n@0104\n"));
+}
+
+#[test]
+fn contiguous_programs_have_one_jump() {
+    let program = assemble_ok("readmem_contiguous", ".start [0x20]\nadd r1, r2\nhlt\n");
+    assert_eq!(program.render_readmem(false), "@0020\n1202\n0000\n");
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use basm::*;
+use common::*;
+
+#[test]
+fn rept_count_is_an_expression() {
+    let program = assemble_ok(
+        "rept_count",
+        "const SIZE = 4\n.start [0x100]\n.rept (SIZE >> 1)\n    add r1, r2\n.endr\nhlt\n",
+    );
+    assert_eq!(program.binary, [0x1202, 0x1202, 0x0000]);
+}
+
+#[test]
+fn rept_count_errors_are_its_own() {
+    let errors = assemble_err(
+        "rept_unknown",
+        ".start [0x100]\n.rept (MISSING + 1)\n    add r1, r2\n.endr\nhlt\n",
+    );
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].code, DiagnosticCode::UnknownConstant);
+
+    let errors = assemble_err("rept_empty", ".start [0x100]\n.rept\nhlt\n.endr\n");
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].message.contains("succeeded by a count"));
+}
+
+#[test]
+fn listing_has_a_row_per_copy() {
+    let program = assemble_ok(
+        "rept_listing",
+        ".start [0x100]\n.rept 2\n    .fill 2, 7\n    add r1, r2\n.endr\nhlt\n",
+    );
+    let listing = program.render_listing();
+    // address, line number and text of the rows that show a source line
+    let sourced: Vec<(String, String, String)> = listing
+        .lines()
+        .filter(|row| !row.starts_with([' ', ';']) && !row.is_empty())
+        .filter_map(|row| {
+            let mut fields = row.split_whitespace().filter(|f| *f != "+");
+            let address = fields.next()?.to_string();
+            let line = fields.nth(2)?.to_string();
+            Some((address, line, fields.collect::<Vec<_>>().join(" ")))
+        })
+        .collect();
+    let expected = [
+        ("0100", "3", ".fill 2, 7"),
+        ("0102", "4", "add r1, r2"),
+        ("0103", "3", ".fill 2, 7"),
+        ("0105", "4", "add r1, r2"),
+        ("0106", "6", "hlt"),
+    ]
+    .map(|(a, l, t)| (a.to_string(), l.to_string(), t.to_string()));
+    assert_eq!(sourced, expected);
+    // the body isn't shown again after its copies
+    assert_eq!(listing.matches("add r1, r2").count(), 2);
+}
+
+#[test]
+fn listing_shows_nested_headers() {
+    let program = assemble_ok(
+        "rept_nested_listing",
+        ".start [0x100]\n.rept 2\n.irp reg, r1, r2\n    mov %reg, 1\n.endr\n.endr\nhlt\n",
+    );
+    let listing = program.render_listing();
+    let lines: Vec<&str> = listing.lines().map(str::trim_end).collect();
+    let header = lines
+        .iter()
+        .position(|row| row.ends_with("3  .irp reg, r1, r2"));
+    let first_copy = lines.iter().position(|row| row.ends_with("mov %reg, 1"));
+    assert!(
+        header.is_some_and(|header| Some(header + 1) == first_copy),
+        "{listing}"
+    );
+    assert_eq!(listing.matches(".irp").count(), 1);
+}
+
+#[test]
+fn repeats_that_cannot_fit_are_errors() {
+    let errors = assemble_err(
+        "rept_huge",
+        ".start [0x100]\n.rept 100000000\n    add r1, r2\n.endr\nhlt\n",
+    );
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].message.contains("too many copies"));
+
+    // nested blocks count towards the same limit, and it's reported once
+    let errors = assemble_err(
+        "rept_nested_huge",
+        ".start [0x100]\n.rept 300\n.rept 300\n    add r1, r2\n.endr\n.endr\n\
+         .rept 70000\n.endr\nhlt\n",
+    );
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].message.contains("too many copies"));
+}